@@ -0,0 +1,69 @@
+use assembly_anarchy::game::*;
+use bevy::prelude::*;
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+
+const POINTS_PER_STROKE: usize = 50;
+
+// A busy level: `drawn` stroke points in short diagonal strokes across the screen,
+// with `items` falling through them.
+fn busy_level(drawn: usize, items: usize) -> HeadlessSimulation {
+    let mut sim = HeadlessSimulation::new(1);
+    let world = sim.app.world_mut();
+
+    for stroke in 0..drawn / POINTS_PER_STROKE {
+        let from = Vec2::new(
+            -950.0 + (stroke % 20) as f32 * 95.0,
+            450.0 - (stroke / 20) as f32 * 90.0,
+        );
+        let to = from + Vec2::new(160.0, -60.0);
+        let points = (0..POINTS_PER_STROKE)
+            .map(|step| from.lerp(to, step as f32 / POINTS_PER_STROKE as f32))
+            .collect();
+        world.spawn((
+            Position(Vec2::ZERO),
+            StrokePhysics {
+                points,
+                radius: STROKE_RADIUS,
+            },
+        ));
+    }
+
+    for item in 0..items {
+        let position = Vec2::new(
+            -900.0 + (item % 40) as f32 * 45.0,
+            450.0 - (item / 40) as f32 * 36.0,
+        );
+        world.spawn((
+            Item::Iron,
+            Position(position),
+            CirclePhysics { radius: 14.0 },
+            Velocity(Vec2::ZERO),
+        ));
+    }
+
+    // the first tick inserts all strokes into the broadphase
+    sim.step(1);
+    sim
+}
+
+fn physics_tick(c: &mut Criterion) {
+    let mut group = c.benchmark_group("physics_tick");
+    group.sample_size(20);
+    for (drawn, items) in [(1_000, 100), (2_500, 250), (5_000, 500), (10_000, 1_000)] {
+        group.bench_with_input(
+            BenchmarkId::new("stroke_points/items", format!("{}/{}", drawn, items)),
+            &(drawn, items),
+            |b, &(drawn, items)| {
+                b.iter_batched_ref(
+                    || busy_level(drawn, items),
+                    |sim| sim.step(1),
+                    BatchSize::PerIteration,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, physics_tick);
+criterion_main!(benches);
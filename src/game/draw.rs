@@ -1,9 +1,141 @@
 use crate::game::*;
 use bevy::color::palettes::basic::*;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use std::f32::consts::TAU;
 
 #[derive(Component)]
 pub struct DrawIndicator;
 
+#[derive(Component)]
+pub struct EraseIndicator;
+
+// Something the player drew, in world space. Kept on the entity for the eraser,
+// the ink count and the undo history.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Drawing {
+    Stroke(Vec<Vec2>), // points `STROKE_SPACING` apart
+    Rectangle(Rect),
+}
+
+impl Drawing {
+    // every stroke point costs one ink, a rectangle as much as a stroke around it
+    pub fn ink(&self) -> u32 {
+        match self {
+            Drawing::Stroke(points) => points.len() as u32,
+            Drawing::Rectangle(rect) => {
+                (2.0 * (rect.width() + rect.height()) / STROKE_SPACING).ceil() as u32
+            }
+        }
+    }
+}
+
+// placed by the player, can be erased again
+#[derive(Component, Debug)]
+pub struct DrawnObstacle(pub Drawing);
+
+pub const STROKE_RADIUS: f32 = 12.;
+// distance between the points of a stroke, however fast the mouse moves
+pub const STROKE_SPACING: f32 = 8.;
+const STROKE_CAP_VERTICES: u32 = 12;
+
+// stroke points closer than this to the cursor are erased
+pub const ERASER_RADIUS: f32 = 24.;
+
+// Ink is stroke length in steps of `STROKE_SPACING`.
+pub fn ink_used<'a>(drawn: impl Iterator<Item = &'a DrawnObstacle>) -> u32 {
+    drawn.map(|drawn| drawn.0.ink()).sum()
+}
+
+// points that can still be drawn, None if the level has no ink limit.
+// Erasing or undoing a stroke gives its ink back.
+pub fn ink_left(budget: &InkBudget, used: u32) -> Option<u32> {
+    budget.0.map(|ink| ink.saturating_sub(used))
+}
+
+// Appends points towards `target` every `STROKE_SPACING`, at most `max_points`.
+// Returns false if the stroke didn't grow, e.g. when the mouse moved less than the spacing.
+pub fn extend_stroke(points: &mut Vec<Vec2>, target: Vec2, max_points: usize) -> bool {
+    let Some(&last) = points.last() else {
+        return false;
+    };
+    let steps = ((last.distance(target) / STROKE_SPACING) as usize).min(max_points);
+    let direction = (target - last).normalize_or_zero();
+    points.extend((1..=steps).map(|step| last + direction * STROKE_SPACING * step as f32));
+    steps > 0
+}
+
+// a disc on every point and a quad along every segment
+fn push_stroke(
+    points: &[Vec2],
+    radius: f32,
+    positions: &mut Vec<[f32; 3]>,
+    indices: &mut Vec<u32>,
+) {
+    for point in points {
+        let center = positions.len() as u32;
+        positions.push([point.x, point.y, 0.]);
+        for i in 0..STROKE_CAP_VERTICES {
+            let rim =
+                *point + Vec2::from_angle(i as f32 * TAU / STROKE_CAP_VERTICES as f32) * radius;
+            positions.push([rim.x, rim.y, 0.]);
+            indices.extend([
+                center,
+                center + 1 + i,
+                center + 1 + (i + 1) % STROKE_CAP_VERTICES,
+            ]);
+        }
+    }
+    for (start, end) in points.iter().zip(points.iter().skip(1)) {
+        let side = (*end - *start).perp().normalize_or_zero() * radius;
+        push_quad(
+            [*start + side, *start - side, *end - side, *end + side],
+            positions,
+            indices,
+        );
+    }
+}
+
+// corners counter-clockwise
+fn push_quad(corners: [Vec2; 4], positions: &mut Vec<[f32; 3]>, indices: &mut Vec<u32>) {
+    let first = positions.len() as u32;
+    positions.extend(corners.map(|corner| [corner.x, corner.y, 0.]));
+    indices.extend([first, first + 1, first + 2, first, first + 2, first + 3]);
+}
+
+// one mesh for all the drawings, used for drawn obstacles and the shape preview
+pub fn drawings_mesh(drawings: &[Drawing]) -> Mesh {
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+    for drawing in drawings {
+        match drawing {
+            Drawing::Stroke(points) => {
+                push_stroke(points, STROKE_RADIUS, &mut positions, &mut indices)
+            }
+            Drawing::Rectangle(rect) => push_quad(
+                [
+                    rect.min,
+                    Vec2::new(rect.max.x, rect.min.y),
+                    rect.max,
+                    Vec2::new(rect.min.x, rect.max.y),
+                ],
+                &mut positions,
+                &mut indices,
+            ),
+        }
+    }
+    let vertex_count = positions.len();
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::default(),
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0., 0., 1.]; vertex_count])
+    .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0., 0.]; vertex_count])
+    .with_inserted_indices(Indices::U32(indices))
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn draw_obstacle(
     mut commands: Commands,
     buttons: Res<ButtonInput<MouseButton>>,
@@ -14,7 +146,25 @@ pub fn draw_obstacle(
     dragging: Res<Dragging>,
     tool_selected: Res<SelectedTool>,
     mut draw_indicator: Query<(&mut Transform), With<DrawIndicator>>,
+    mut history: ResMut<EditHistory>,
+    ink_budget: Res<InkBudget>,
+    // every drawing counts towards the ink, rectangles have no `StrokePhysics`
+    mut drawings: Query<(&mut DrawnObstacle, Option<&mut StrokePhysics>, &Mesh2d)>,
+    mut active_stroke: Local<Option<Entity>>,
 ) {
+    // a stroke ends when the mouse is released or the cursor moves over a clickable element
+    let drawing = buttons.pressed(MouseButton::Left)
+        && tool_selected.0 == Tools::Draw
+        && over_element.0.is_none()
+        && dragging.entity.is_none();
+    if !drawing {
+        if let Some(entity) = active_stroke.take() {
+            if let Ok((drawn, _, _)) = drawings.get(entity) {
+                history.record_drawn(vec![drawn.0.clone()]);
+            }
+        }
+    }
+
     if (tool_selected.0 == Tools::Draw) {
         if let Some(_) = over_element.0 {
             // move indicator off screen
@@ -53,19 +203,36 @@ pub fn draw_obstacle(
                 .with_scale(Vec3::splat(24.));
             }
 
-            //draw
-            if (buttons.pressed(MouseButton::Left)) {
-                commands.spawn((
-                    Mesh2d(meshes.add(Circle::default())),
-                    MeshMaterial2d(materials.add(Color::from(BLACK))),
-                    Transform::from_xyz(mouse_position.0.x, mouse_position.0.y, 1.)
-                        .with_scale(Vec3::splat(24.)),
-                    Position(Vec2 {
-                        x: mouse_position.0.x,
-                        y: mouse_position.0.y,
-                    }),
-                    CirclePhysics { radius: 12. },
-                ));
+            //draw, unless the ink ran out (the ink text turns red)
+            if buttons.pressed(MouseButton::Left) {
+                let ink = ink_left(
+                    &ink_budget,
+                    ink_used(drawings.iter().map(|(drawn, _, _)| drawn)),
+                )
+                .map_or(usize::MAX, |ink| ink as usize);
+                match *active_stroke {
+                    None if ink > 0 => {
+                        *active_stroke = Some(spawn_drawing(
+                            commands.reborrow(),
+                            &mut meshes,
+                            &mut materials,
+                            Drawing::Stroke(vec![mouse_position.0]),
+                        ));
+                    }
+                    Some(entity) => {
+                        if let Ok((mut drawn, Some(mut stroke), mesh)) = drawings.get_mut(entity) {
+                            if let Drawing::Stroke(points) = &mut drawn.0 {
+                                if extend_stroke(points, mouse_position.0, ink) {
+                                    stroke.points.clone_from(points);
+                                    if let Some(mesh) = meshes.get_mut(&mesh.0) {
+                                        *mesh = drawings_mesh(std::slice::from_ref(&drawn.0));
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    None => {}
+                }
             }
         }
     } else {
@@ -80,3 +247,109 @@ pub fn draw_obstacle(
         }
     }
 }
+
+// Drawings are in world space and their entity stays at the origin,
+// so drawings taken from the undo history compare equal to the spawned ones.
+pub fn spawn_drawing(
+    mut commands: Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    drawing: Drawing,
+) -> Entity {
+    let mut entity = commands.spawn((
+        Mesh2d(meshes.add(drawings_mesh(std::slice::from_ref(&drawing)))),
+        MeshMaterial2d(materials.add(Color::from(BLACK))),
+        Transform::from_xyz(0., 0., 1.),
+        Position(Vec2::ZERO),
+        LevelEntity,
+    ));
+    match &drawing {
+        Drawing::Stroke(points) => entity.insert(StrokePhysics {
+            points: points.clone(),
+            radius: STROKE_RADIUS,
+        }),
+        Drawing::Rectangle(rect) => entity.insert(ColliderCollection(vec![RectanglePhysics {
+            width: rect.width(),
+            height: rect.height(),
+            offset_x: rect.center().x,
+            offset_y: rect.center().y,
+        }])),
+    };
+    entity.insert(DrawnObstacle(drawing)).id()
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn erase_obstacles(
+    mut commands: Commands,
+    buttons: Res<ButtonInput<MouseButton>>,
+    mouse_pos: Res<MouseWorldPosition>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    over_element: Res<OverClickableElement>,
+    dragging: Res<Dragging>,
+    tool_selected: Res<SelectedTool>,
+    drawn: Query<(Entity, &DrawnObstacle)>,
+    mut erase_indicator: Query<&mut Transform, With<EraseIndicator>>,
+    mut history: ResMut<EditHistory>,
+) {
+    let mut transform = erase_indicator.single_mut();
+    // don't erase over clickable elements or while dragging
+    let brush = mouse_pos.0.filter(|_| {
+        tool_selected.0 == Tools::Erase && over_element.0.is_none() && dragging.entity.is_none()
+    });
+    let Some(brush) = brush else {
+        // move indicator off screen
+        *transform = Transform::from_xyz(10000., 10000., -1000.);
+        return;
+    };
+    *transform = Transform::from_xyz(brush.0.x, brush.0.y, 90.);
+
+    if buttons.pressed(MouseButton::Left) {
+        erase_at(
+            commands,
+            &mut meshes,
+            &mut materials,
+            &drawn,
+            brush.0,
+            &mut history,
+        );
+    }
+}
+
+pub fn erase_at(
+    mut commands: Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    drawn: &Query<(Entity, &DrawnObstacle)>,
+    brush: Vec2,
+    history: &mut EditHistory,
+) {
+    for (entity, drawn) in drawn.iter() {
+        let pieces = match &drawn.0 {
+            Drawing::Stroke(points) => {
+                let erased = |point: &Vec2| point.distance(brush) < ERASER_RADIUS;
+                if !points.iter().any(erased) {
+                    continue;
+                }
+                // the stroke falls apart into the runs of points outside the eraser, lone points are dropped
+                points
+                    .split(erased)
+                    .filter(|piece| piece.len() > 1)
+                    .map(|piece| Drawing::Stroke(piece.to_vec()))
+                    .collect()
+            }
+            // rectangles are erased as a whole
+            Drawing::Rectangle(rect) => {
+                if !rect.inflate(ERASER_RADIUS).contains(brush) {
+                    continue;
+                }
+                Vec::new()
+            }
+        };
+        commands.entity(entity).despawn();
+        for piece in &pieces {
+            spawn_drawing(commands.reborrow(), meshes, materials, piece.clone());
+        }
+        history.record_erased(drawn.0.clone(), pieces);
+    }
+}
@@ -0,0 +1,268 @@
+use crate::game::*;
+
+// A player action that can be undone. Drawings are remembered by their shape,
+// as undoing and redoing spawns new entities for them.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Edit {
+    // drawing adds strokes or shapes, erasing removes them and adds back what is left of them
+    Drawings {
+        removed: Vec<Drawing>,
+        added: Vec<Drawing>,
+    },
+    // a drag can turn the machine as well, the rotations are in radians
+    Move {
+        entity: Entity,
+        from: Vec2,
+        to: Vec2,
+        from_rotation: f32,
+        to_rotation: f32,
+    },
+}
+
+impl Edit {
+    // the edit that reverts this one
+    pub fn inverse(&self) -> Edit {
+        match self {
+            Edit::Drawings { removed, added } => Edit::Drawings {
+                removed: added.clone(),
+                added: removed.clone(),
+            },
+            Edit::Move {
+                entity,
+                from,
+                to,
+                from_rotation,
+                to_rotation,
+            } => Edit::Move {
+                entity: *entity,
+                from: *to,
+                to: *from,
+                from_rotation: *to_rotation,
+                to_rotation: *from_rotation,
+            },
+        }
+    }
+
+    // ink the edit takes, negative if it gives ink back
+    pub fn ink_needed(&self) -> i64 {
+        match self {
+            Edit::Drawings { removed, added } => {
+                let ink = |drawings: &Vec<Drawing>| drawings.iter().map(Drawing::ink).sum::<u32>();
+                ink(added) as i64 - ink(removed) as i64
+            }
+            Edit::Move { .. } => 0,
+        }
+    }
+
+    fn fits(&self, ink_left: Option<u32>) -> bool {
+        ink_left.is_none_or(|ink| self.ink_needed() <= ink as i64)
+    }
+}
+
+// Undo and redo stacks of the current level, cleared when a level is loaded.
+#[derive(Resource, Default, Debug)]
+pub struct EditHistory {
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+    stroke: Option<Edit>,                // erased since the mouse went down
+    moving: Option<(Entity, Vec2, f32)>, // dragged entity, where the drag started and its rotation then
+}
+
+impl EditHistory {
+    pub fn record(&mut self, edit: Edit) {
+        self.finish_stroke();
+        self.undo.push(edit);
+        self.redo.clear();
+    }
+
+    pub fn record_drawn(&mut self, drawings: Vec<Drawing>) {
+        self.record(Edit::Drawings {
+            removed: Vec::new(),
+            added: drawings,
+        });
+    }
+
+    // `pieces` are what is left of `drawing` outside the eraser
+    pub fn record_erased(&mut self, drawing: Drawing, pieces: Vec<Drawing>) {
+        let edit = self.stroke.get_or_insert_with(|| Edit::Drawings {
+            removed: Vec::new(),
+            added: Vec::new(),
+        });
+        let Edit::Drawings { removed, added } = edit else {
+            return;
+        };
+        // pieces left by the same eraser stroke weren't there before it
+        match added.iter().position(|piece| *piece == drawing) {
+            Some(index) => {
+                added.swap_remove(index);
+            }
+            None => removed.push(drawing),
+        }
+        added.extend(pieces);
+    }
+
+    // an eraser stroke becomes a single undo step once the mouse is released
+    pub fn finish_stroke(&mut self) {
+        if let Some(stroke) = self.stroke.take() {
+            self.record(stroke);
+        }
+    }
+
+    // returns the edit that has to be applied to undo the last one,
+    // None if there is nothing to undo or it would take more ink than is left
+    pub fn undo(&mut self, ink_left: Option<u32>) -> Option<Edit> {
+        self.finish_stroke();
+        let inverse = self.undo.last()?.inverse();
+        if !inverse.fits(ink_left) {
+            return None;
+        }
+        let edit = self.undo.pop()?;
+        self.redo.push(edit);
+        Some(inverse)
+    }
+
+    // returns the edit that has to be applied again
+    pub fn redo(&mut self, ink_left: Option<u32>) -> Option<Edit> {
+        self.finish_stroke();
+        if !self.redo.last()?.fits(ink_left) {
+            return None;
+        }
+        let edit = self.redo.pop()?;
+        self.undo.push(edit.clone());
+        Some(edit)
+    }
+
+    pub fn clear(&mut self) {
+        *self = EditHistory::default();
+    }
+}
+
+// `positions` holds the dragged machines and the drawn obstacles, `rotations` the machines
+pub fn apply_edit(
+    mut commands: Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    positions: &mut Query<(Entity, &mut Position, Option<&DrawnObstacle>)>,
+    rotations: &mut Query<&mut Rotation>,
+    edit: Edit,
+) {
+    match edit {
+        Edit::Drawings { removed, added } => {
+            let mut drawn: Vec<(Entity, &DrawnObstacle)> = positions
+                .iter()
+                .filter_map(|(entity, _, drawn)| Some((entity, drawn?)))
+                .collect();
+            for drawing in removed {
+                if let Some(index) = drawn.iter().position(|(_, drawn)| drawn.0 == drawing) {
+                    let (entity, _) = drawn.swap_remove(index);
+                    commands.entity(entity).despawn();
+                }
+            }
+            for drawing in added {
+                spawn_drawing(commands.reborrow(), meshes, materials, drawing);
+            }
+        }
+        Edit::Move {
+            entity,
+            to,
+            to_rotation,
+            ..
+        } => {
+            // the entity may be gone by now
+            if let Ok((_, mut pos, _)) = positions.get_mut(entity) {
+                pos.0 = to;
+            }
+            if let Ok(mut rotation) = rotations.get_mut(entity) {
+                rotation.0 = to_rotation;
+            }
+        }
+    }
+}
+
+// ink left over by the drawn obstacles in `positions`
+pub fn ink_left_in(
+    ink_budget: &InkBudget,
+    positions: &Query<(Entity, &mut Position, Option<&DrawnObstacle>)>,
+) -> Option<u32> {
+    ink_left(
+        ink_budget,
+        ink_used(positions.iter().filter_map(|(_, _, drawn)| drawn)),
+    )
+}
+
+// Ctrl+Z undoes the last drawing or move, Ctrl+Y (or Ctrl+Shift+Z) redoes it
+#[allow(clippy::too_many_arguments)]
+pub fn undo_redo(
+    commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut history: ResMut<EditHistory>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut positions: Query<(Entity, &mut Position, Option<&DrawnObstacle>)>,
+    mut rotations: Query<&mut Rotation>,
+    ink_budget: Res<InkBudget>,
+) {
+    if !keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        return;
+    }
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let ink = ink_left_in(&ink_budget, &positions);
+    let edit = if keys.just_pressed(KeyCode::KeyY) || (shift && keys.just_pressed(KeyCode::KeyZ)) {
+        history.redo(ink)
+    } else if keys.just_pressed(KeyCode::KeyZ) {
+        history.undo(ink)
+    } else {
+        None
+    };
+    if let Some(edit) = edit {
+        apply_edit(
+            commands,
+            &mut meshes,
+            &mut materials,
+            &mut positions,
+            &mut rotations,
+            edit,
+        );
+    }
+}
+
+pub fn finish_strokes(buttons: Res<ButtonInput<MouseButton>>, mut history: ResMut<EditHistory>) {
+    if !buttons.pressed(MouseButton::Left) {
+        history.finish_stroke();
+    }
+}
+
+// Has to run before `reset_dragging` and `update_dragged_object`, so it sees where a drag starts and ends.
+pub fn record_moves(
+    dragging: Res<Dragging>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    positions: Query<(&Position, Option<&Rotation>)>,
+    mut history: ResMut<EditHistory>,
+) {
+    let Some(entity) = dragging.entity else {
+        return;
+    };
+    let Ok((pos, rotation)) = positions.get(entity) else {
+        return;
+    };
+    let rotation = rotation.map_or(0.0, |rotation| rotation.0);
+    let (from, from_rotation) = match history.moving {
+        Some((moving, from, from_rotation)) if moving == entity => (from, from_rotation),
+        _ => {
+            history.moving = Some((entity, pos.0, rotation));
+            return;
+        }
+    };
+    if !buttons.pressed(MouseButton::Left) {
+        history.moving = None;
+        if from != pos.0 || from_rotation != rotation {
+            history.record(Edit::Move {
+                entity,
+                from,
+                to: pos.0,
+                from_rotation,
+                to_rotation: rotation,
+            });
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use crate::game::*;
+use std::collections::VecDeque;
+
+// seconds of transactions the income meter averages over
+pub const INCOME_WINDOW: f32 = 5.0;
+
+// Production money changes of the last `INCOME_WINDOW` simulated seconds, see `MoneyReason::is_production`.
+#[derive(Resource, Default)]
+pub struct IncomeMeter {
+    transactions: VecDeque<(f32, i32)>, // simulated time, amount
+}
+
+impl IncomeMeter {
+    pub fn income_per_second(&self) -> f32 {
+        self.sum(|amount| amount > 0) as f32 / INCOME_WINDOW
+    }
+
+    // positive, like the costs shown on the input pipes
+    pub fn expenses_per_second(&self) -> f32 {
+        -self.sum(|amount| amount < 0) as f32 / INCOME_WINDOW
+    }
+
+    fn sum(&self, filter: impl Fn(i32) -> bool) -> i32 {
+        self.transactions
+            .iter()
+            .map(|(_, amount)| *amount)
+            .filter(|amount| filter(*amount))
+            .sum()
+    }
+
+    pub fn clear(&mut self) {
+        self.transactions.clear();
+    }
+}
+
+#[derive(Component)]
+pub struct IncomeText;
+
+#[derive(Component)]
+pub struct ExpensesText;
+
+// Reads the fixed clock, so the meter shows money per simulated second at any speed.
+pub fn record_transactions(
+    mut money_changes: EventReader<MoneyChanged>,
+    time: Res<Time<Fixed>>,
+    mut meter: ResMut<IncomeMeter>,
+) {
+    let now = time.elapsed_secs();
+    for change in money_changes.read() {
+        if change.reason.is_production() {
+            meter.transactions.push_back((now, change.amount));
+        }
+    }
+    while meter
+        .transactions
+        .front()
+        .is_some_and(|(time, _)| now - time > INCOME_WINDOW)
+    {
+        meter.transactions.pop_front();
+    }
+}
+
+pub fn reset_income_meter(mut meter: ResMut<IncomeMeter>) {
+    meter.clear();
+}
+
+// input and output pipes, the flag is set for input pipes
+type Pipes<'w, 's> = Query<
+    'w,
+    's,
+    (&'static Position, &'static SpriteView, Has<InputPipe>),
+    Or<(With<InputPipe>, With<OutputPipe>)>,
+>;
+
+// "+5" and "-1" rising from the pipes
+pub fn spawn_money_popups(
+    mut commands: Commands,
+    mut money_changes: EventReader<MoneyChanged>,
+    pipes: Pipes,
+    asset_server: Res<AssetServer>,
+) {
+    for change in money_changes.read() {
+        let Some(Ok((pipe_pos, pipe_view, input))) =
+            change.source_entity.map(|pipe| pipes.get(pipe))
+        else {
+            continue;
+        };
+        // under input pipes, over output pipes
+        let half_height = pipe_view.get_scale().y / 2.0;
+        let position = if input {
+            pipe_pos.0 - Vec2::new(0., half_height + 30.)
+        } else {
+            pipe_pos.0 + Vec2::new(0., half_height)
+        };
+        let (text, color) = if change.amount >= 0 {
+            (
+                format!("+{}", change.amount),
+                Color::srgb(1.0, 215.0 / 255.0, 0.0),
+            )
+        } else {
+            (
+                format!("{}", change.amount),
+                Color::srgb(1.0, 130.0 / 255.0, 130.0 / 255.0),
+            )
+        };
+        spawn_floating_text(commands.reborrow(), &asset_server, text, color, position);
+    }
+}
+
+pub fn update_income_texts(
+    meter: Res<IncomeMeter>,
+    mut income_text: Query<&mut Text, (With<IncomeText>, Without<ExpensesText>)>,
+    mut expenses_text: Query<&mut Text, (With<ExpensesText>, Without<IncomeText>)>,
+) {
+    income_text.single_mut().0 = format!("+{:.1}/s", meter.income_per_second());
+    expenses_text.single_mut().0 = format!("-{:.1}/s", meter.expenses_per_second());
+}
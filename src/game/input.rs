@@ -1,6 +1,8 @@
 use std::process::CommandArgs;
 
 use crate::game::*;
+use bevy::input::mouse::MouseWheel;
+use bevy::ui::RelativeCursorPosition;
 use bevy::winit::cursor::CursorIcon;
 use bevy::{
     state::commands,
@@ -32,6 +34,11 @@ pub struct JustClicked(pub Option<Entity>);
 #[derive(Resource)]
 pub struct OverClickableElement(pub Option<Entity>);
 
+// ui panels the mouse can't draw, place or sell through, like the machine shop
+#[derive(Component, Default)]
+#[require(RelativeCursorPosition)]
+pub struct BlocksWorldInput;
+
 pub fn update_dragged_object(
     dragging: Res<Dragging>,
     mouse_pos: Res<MouseWorldPosition>,
@@ -51,6 +58,33 @@ pub fn update_dragged_object(
     }
 }
 
+// a quarter of a right angle per key press or scroll step
+pub const ROTATION_STEP: f32 = std::f32::consts::FRAC_PI_8;
+
+// Q and scrolling up turn the dragged machine counter-clockwise, E and scrolling down clockwise
+pub fn rotate_dragged_machine(
+    dragging: Res<Dragging>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut scroll: EventReader<MouseWheel>,
+    mut machines: Query<&mut Rotation, With<Machine>>,
+) {
+    let mut steps: f32 = scroll.read().map(|event| event.y.signum()).sum();
+    if keys.just_pressed(KeyCode::KeyQ) {
+        steps += 1.0;
+    }
+    if keys.just_pressed(KeyCode::KeyE) {
+        steps -= 1.0;
+    }
+    let Some(entity) = dragging.entity else {
+        return;
+    };
+    if let Ok(mut rotation) = machines.get_mut(entity) {
+        if steps != 0.0 {
+            rotation.0 = (rotation.0 + steps * ROTATION_STEP).rem_euclid(std::f32::consts::TAU);
+        }
+    }
+}
+
 pub fn reset_dragging(mut dragging: ResMut<Dragging>, buttons: Res<ButtonInput<MouseButton>>) {
     if (!buttons.pressed(MouseButton::Left)) {
         dragging.entity = None;
@@ -112,7 +146,7 @@ pub fn update_just_clicked(
     mouse_pos: Res<MouseWorldPosition>,
     mut just_clicked: ResMut<JustClicked>,
     mut over_element: ResMut<OverClickableElement>,
-    views: Query<(Entity, &SpriteView, &Position), With<Clickable>>,
+    views: Query<(Entity, &SpriteView, &Position, Option<&Rotation>), With<Clickable>>,
     mut commands: Commands,
 ) {
     just_clicked.0 = None;
@@ -123,7 +157,7 @@ pub fn update_just_clicked(
     };
 
     // Iterate over all entities that are `Clickable` and have a `SpriteView` and `Position`.
-    for (entity, sprite_view, entity_pos) in views.iter() {
+    for (entity, sprite_view, entity_pos, rotation) in views.iter() {
         // Get the scale/size of the sprite from its SpriteView
         let sprite_size = sprite_view.get_scale();
         let half_size = sprite_size / 2.0;
@@ -138,8 +172,13 @@ pub fn update_just_clicked(
         // Create a Bevy Rect for easy collision checking.
         let bounding_box = Rect::new(min_x, min_y, max_x, max_y);
 
+        // Turned sprites are checked with the mouse turned back around their center.
+        let rotation = rotation.map_or(Rot2::IDENTITY, Rotation::rot2);
+        let mouse_in_sprite =
+            entity_pos.0 + rotation.inverse() * (mouse_world_pos.0 - entity_pos.0);
+
         // Check if the click position is within the bounding box.
-        if bounding_box.contains(mouse_world_pos.0) {
+        if bounding_box.contains(mouse_in_sprite) {
             over_element.0 = Some(entity);
             if let Some(click_world_pos) = left_mouse_click_pos.0 {
                 just_clicked.0 = Some(entity);
@@ -148,3 +187,15 @@ pub fn update_just_clicked(
         }
     }
 }
+
+// ui isn't `Clickable`, so this has to run after `update_just_clicked`
+pub fn block_input_over_ui(
+    panels: Query<(Entity, &RelativeCursorPosition), With<BlocksWorldInput>>,
+    mut over_element: ResMut<OverClickableElement>,
+) {
+    for (panel, cursor) in panels.iter() {
+        if cursor.mouse_over() {
+            over_element.0 = Some(panel);
+        }
+    }
+}
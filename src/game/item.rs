@@ -1,7 +1,8 @@
 use crate::game::*;
+use serde::Deserialize;
 use std::fmt;
 
-#[derive(Component, Debug, Copy, Clone, PartialEq)]
+#[derive(Component, Debug, Copy, Clone, PartialEq, Deserialize)]
 pub enum Item {
     Bolt,
     Steel,
@@ -0,0 +1,277 @@
+use crate::game::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+// Levels are described by `.ron` files in `assets/Levels/`, named `level_<number>.ron`.
+pub const LEVEL_DIRECTORY: &str = "Levels";
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct LevelDefinition {
+    pub starting_money: i32,
+    pub money_goal: i32,
+    pub background: Background,
+    #[serde(default)]
+    pub tool_buttons: bool, // show the mouse / draw tool buttons
+    #[serde(default)]
+    pub explainer: bool, // only shows its hints, counts as won right away
+    #[serde(default)]
+    pub ink: Option<u32>, // obstacles the player can draw, unlimited if not set
+    #[serde(default)]
+    pub build_phase: bool, // input pipes stay locked until the run button is pressed
+    #[serde(default = "default_refund_percent")]
+    pub refund_percent: u32, // of the cost, paid back for selling a machine
+    #[serde(default)]
+    pub input_pipes: Vec<InputPipeDefinition>,
+    #[serde(default)]
+    pub output_pipes: Vec<OutputPipeDefinition>,
+    #[serde(default)]
+    pub machine_shop: Vec<Machine>,
+    #[serde(default)]
+    pub machine_limits: HashMap<usize, u32>, // shop slot -> how many can be bought, unlimited if not set
+    #[serde(default)]
+    pub hints: Vec<Hint>,
+    #[serde(default)]
+    pub conveyors: Vec<ConveyorDefinition>, // pre-placed belts, can't be moved by the player
+}
+
+pub const DEFAULT_REFUND_PERCENT: u32 = 50;
+
+fn default_refund_percent() -> u32 {
+    DEFAULT_REFUND_PERCENT
+}
+
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub enum Background {
+    Industry,
+    Industry2,
+    City,
+    City2,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct InputPipeDefinition {
+    pub slot: u8, // 2..=5, slot 1 is blocked by the money window
+    pub item: Item,
+    pub spawn_rate: f32,
+    pub cost: i32,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct OutputPipeDefinition {
+    pub slot: u8, // 1..=5
+    pub item: Item,
+    pub reward: i32,
+    #[serde(default)]
+    pub wrong_items: WrongItems,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ConveyorDefinition {
+    pub x: f32,
+    pub y: f32,
+    pub direction: BeltDirection,
+    pub speed: f32,
+}
+
+// tutorial hints shown while the level is active
+#[derive(Deserialize, Debug, Clone)]
+pub enum Hint {
+    PointLeft { x: f32, y: f32 },
+    PointRight { x: f32, y: f32 },
+    Title(String),
+    Subtitle(String),
+}
+
+#[derive(Debug)]
+pub enum LevelLoadError {
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+    Parse {
+        path: PathBuf,
+        error: ron::error::SpannedError,
+    },
+    Invalid {
+        path: PathBuf,
+        reason: String,
+    },
+}
+
+impl fmt::Display for LevelLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelLoadError::Io { path, error } => {
+                write!(f, "could not read level file {}: {}", path.display(), error)
+            }
+            LevelLoadError::Parse { path, error } => {
+                write!(f, "malformed level file {}:{}", path.display(), error)
+            }
+            LevelLoadError::Invalid { path, reason } => {
+                write!(f, "invalid level file {}: {}", path.display(), reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LevelLoadError {}
+
+impl Background {
+    pub fn get_sprite_view(&self) -> SpriteView {
+        match self {
+            Background::Industry => SpriteView::BackgroundIndustry,
+            Background::Industry2 => SpriteView::BackgroundIndustry2,
+            Background::City => SpriteView::BackgroundCity,
+            Background::City2 => SpriteView::BackgroundCity2,
+        }
+    }
+}
+
+pub fn input_pipe_position(slot: u8) -> Option<Vec2> {
+    match slot {
+        2 => Some(INPUT_PIPE_POS2),
+        3 => Some(INPUT_PIPE_POS3),
+        4 => Some(INPUT_PIPE_POS4),
+        5 => Some(INPUT_PIPE_POS5),
+        _ => None,
+    }
+}
+
+pub fn output_pipe_position(slot: u8) -> Option<Vec2> {
+    match slot {
+        1 => Some(OUTPUT_PIPE_POS1),
+        2 => Some(OUTPUT_PIPE_POS2),
+        3 => Some(OUTPUT_PIPE_POS3),
+        4 => Some(OUTPUT_PIPE_POS4),
+        5 => Some(OUTPUT_PIPE_POS5),
+        _ => None,
+    }
+}
+
+impl LevelDefinition {
+    pub fn parse(path: PathBuf, source: &str) -> Result<LevelDefinition, LevelLoadError> {
+        let level: LevelDefinition = match ron::from_str(source) {
+            Ok(level) => level,
+            Err(error) => return Err(LevelLoadError::Parse { path, error }),
+        };
+        level.validate(path)?;
+        Ok(level)
+    }
+
+    fn validate(&self, path: PathBuf) -> Result<(), LevelLoadError> {
+        let invalid = |reason: String| LevelLoadError::Invalid {
+            path: path.clone(),
+            reason,
+        };
+        for pipe in &self.input_pipes {
+            if input_pipe_position(pipe.slot).is_none() {
+                return Err(invalid(format!(
+                    "input pipe slot {} does not exist (expected 2 to 5)",
+                    pipe.slot
+                )));
+            }
+        }
+        for pipe in &self.output_pipes {
+            if output_pipe_position(pipe.slot).is_none() {
+                return Err(invalid(format!(
+                    "output pipe slot {} does not exist (expected 1 to 5)",
+                    pipe.slot
+                )));
+            }
+        }
+        for conveyor in &self.conveyors {
+            if conveyor.speed < 0.0 {
+                return Err(invalid(format!(
+                    "conveyor speed {} is negative, use the direction instead",
+                    conveyor.speed
+                )));
+            }
+        }
+        for slot in self.machine_limits.keys() {
+            if *slot >= self.machine_shop.len() {
+                return Err(invalid(format!(
+                    "machine limit for shop slot {} but the shop has {} entries",
+                    slot,
+                    self.machine_shop.len()
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+// "level_3.ron" -> 3
+fn level_number(path: &Path) -> Option<u32> {
+    if path.extension()? != "ron" {
+        return None;
+    }
+    path.file_stem()?
+        .to_str()?
+        .strip_prefix("level_")?
+        .parse()
+        .ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_level_files() -> Vec<Result<(u32, LevelDefinition), LevelLoadError>> {
+    let directory = bevy::asset::io::file::FileAssetReader::get_base_path()
+        .join("assets")
+        .join(LEVEL_DIRECTORY);
+
+    let entries = match std::fs::read_dir(&directory) {
+        Ok(entries) => entries,
+        Err(error) => {
+            return vec![Err(LevelLoadError::Io {
+                path: directory,
+                error,
+            })]
+        }
+    };
+
+    let mut results = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Some(number) = level_number(&path) else {
+            continue;
+        };
+        let result = match std::fs::read_to_string(&path) {
+            Ok(source) => LevelDefinition::parse(path, &source).map(|level| (number, level)),
+            Err(error) => Err(LevelLoadError::Io { path, error }),
+        };
+        results.push(result);
+    }
+    results
+}
+
+// The web build can't read from disk, so the shipped levels are embedded at compile time.
+#[cfg(target_arch = "wasm32")]
+pub fn read_level_files() -> Vec<Result<(u32, LevelDefinition), LevelLoadError>> {
+    macro_rules! embed_levels {
+        ($($file_name:literal),*) => {
+            [$(($file_name, include_str!(concat!("../../assets/Levels/", $file_name)))),*]
+        };
+    }
+    const EMBEDDED_LEVELS: [(&str, &str); 10] = embed_levels!(
+        "level_0.ron",
+        "level_1.ron",
+        "level_2.ron",
+        "level_3.ron",
+        "level_4.ron",
+        "level_5.ron",
+        "level_6.ron",
+        "level_7.ron",
+        "level_8.ron",
+        "level_9.ron"
+    );
+
+    EMBEDDED_LEVELS
+        .iter()
+        .filter_map(|(file_name, source)| {
+            let path = PathBuf::from(LEVEL_DIRECTORY).join(file_name);
+            let number = level_number(&path)?;
+            Some(LevelDefinition::parse(path, source).map(|level| (number, level)))
+        })
+        .collect()
+}
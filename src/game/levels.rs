@@ -5,59 +5,9 @@ use bevy::{state::commands, utils::HashMap};
 #[derive(Resource, Default)]
 pub struct CurrentLevel(pub u32);
 
-// A resource that holds a map of level numbers to their spawn functions.
-#[derive(Resource)]
-pub struct LevelRegistry(
-    pub HashMap<u32, fn(Commands, ResMut<CurrentMoney>, ResMut<MoneyGoal>, Res<AssetServer>) -> ()>,
-); // A map from level number to a spawn function
-
-impl Default for LevelRegistry {
-    fn default() -> Self {
-        let mut map = HashMap::new();
-        // Register your level spawn functions here:
-        map.insert(
-            0u32,
-            load_level_0 as fn(Commands, ResMut<CurrentMoney>, ResMut<MoneyGoal>, Res<AssetServer>),
-        );
-        map.insert(
-            1u32,
-            load_level_1 as fn(Commands, ResMut<CurrentMoney>, ResMut<MoneyGoal>, Res<AssetServer>),
-        );
-        map.insert(
-            2u32,
-            load_level_2 as fn(Commands, ResMut<CurrentMoney>, ResMut<MoneyGoal>, Res<AssetServer>),
-        );
-        map.insert(
-            3u32,
-            load_level_3 as fn(Commands, ResMut<CurrentMoney>, ResMut<MoneyGoal>, Res<AssetServer>),
-        );
-        map.insert(
-            4u32,
-            load_level_4 as fn(Commands, ResMut<CurrentMoney>, ResMut<MoneyGoal>, Res<AssetServer>),
-        );
-        map.insert(
-            5u32,
-            load_level_5 as fn(Commands, ResMut<CurrentMoney>, ResMut<MoneyGoal>, Res<AssetServer>),
-        );
-        map.insert(
-            6u32,
-            load_level_6 as fn(Commands, ResMut<CurrentMoney>, ResMut<MoneyGoal>, Res<AssetServer>),
-        );
-        map.insert(
-            7u32,
-            load_level_7 as fn(Commands, ResMut<CurrentMoney>, ResMut<MoneyGoal>, Res<AssetServer>),
-        );
-        map.insert(
-            8u32,
-            load_level_8 as fn(Commands, ResMut<CurrentMoney>, ResMut<MoneyGoal>, Res<AssetServer>),
-        );
-        map.insert(
-            9u32,
-            load_level_9 as fn(Commands, ResMut<CurrentMoney>, ResMut<MoneyGoal>, Res<AssetServer>),
-        );
-        LevelRegistry(map)
-    }
-}
+// A resource that holds a map of level numbers to their definitions, read from `assets/Levels/`.
+#[derive(Resource, Default)]
+pub struct LevelRegistry(pub HashMap<u32, LevelDefinition>);
 
 #[derive(Resource)]
 pub struct CurrentMoney(pub i32);
@@ -66,212 +16,182 @@ pub struct CurrentMoney(pub i32);
 #[derive(Resource)]
 pub struct MoneyGoal(pub i32);
 
-#[derive(Resource)]
-pub struct LevelWon(pub bool);
+// how many obstacles can be drawn in the current level, None = unlimited
+#[derive(Resource, Default)]
+pub struct InkBudget(pub Option<u32>);
 
+// percentage of a machine's cost paid back when it is sold in the current level
 #[derive(Resource)]
-pub struct LevelLost(pub bool);
+pub struct RefundPercent(pub u32);
 
-pub fn load_initial_level(
-    mut commands: Commands,
-    level_registry: Res<LevelRegistry>,
-    current_level: Res<CurrentLevel>,
-    mut money: ResMut<CurrentMoney>,
-    mut money_goal: ResMut<MoneyGoal>,
-    asset_server: Res<AssetServer>,
-) {
-    if let Some(spawn_fn) = level_registry.0.get(&current_level.0) {
-        spawn_fn(commands, money, money_goal, asset_server);
-    } else {
-        error!(
-            "No spawn function registered for initial level {}",
-            current_level.0
-        );
+// how many of each shop slot can still be bought, None = no limit
+#[derive(Resource, Default)]
+pub struct ShopStock(pub Vec<Option<u32>>);
+
+impl ShopStock {
+    pub fn from_level(level: &LevelDefinition) -> ShopStock {
+        ShopStock(
+            (0..level.machine_shop.len())
+                .map(|slot| level.machine_limits.get(&slot).copied())
+                .collect(),
+        )
     }
-}
 
-pub fn level_management(
-    mut current_money: ResMut<CurrentMoney>,
-    mut money_goal: ResMut<MoneyGoal>,
-    mut level_won: ResMut<LevelWon>,
-    mut level_lost: ResMut<LevelLost>,
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-    mut current_level: ResMut<CurrentLevel>,
-    level_registry: Res<LevelRegistry>,
-    level_entities_query: Query<Entity, With<Position>>, // Query needed for de-spawn
-    mut selected_tool: ResMut<SelectedTool>,
-) {
-    if (current_money.0 >= money_goal.0 && !level_won.0 && !level_lost.0) {
-        level_won.0 = true;
-
-        let mut complete_text = Text::new("Level Complete!");
-        let mut subtext = Text::new("Press Any Key To Continue");
-        if current_level.0 == (level_registry.0.iter().count() - 1) as u32 {
-            complete_text = Text::new("You won!");
-            subtext = Text::new("please leave a rating :)");
-        }
-        commands.spawn((
-            complete_text,
-            TextFont {
-                font: asset_server.load("Fonts/CyberpunkCraftpixPixel.otf"),
-                font_size: 100.,
-                ..default()
-            },
-            TextColor(Color::srgb(255.0 / 255.0, 215.0 / 255.0, 0.0)),
-            TextLayout::new_with_justify(JustifyText::Center),
-            BoxShadow {
-                x_offset: Val::Percent(0.),
-                y_offset: Val::Percent(0.),
-                blur_radius: Val::Percent(2.),
-                ..Default::default()
-            },
-            Node {
-                margin: UiRect {
-                    top: Val::Percent(25.0),
-                    ..Default::default()
-                },
-                width: Val::Percent(100.0),
-                justify_content: JustifyContent::Center,
-                align_items: AlignItems::Center,
-                position_type: PositionType::Absolute,
-                ..default()
-            },
-            Position(Vec2 { x: 0., y: 0. }),
-        ));
-        commands.spawn((
-            subtext,
-            TextFont {
-                font: asset_server.load("Fonts/CyberpunkCraftpixPixel.otf"),
-                font_size: 30.,
-                ..default()
-            },
-            TextColor(Color::srgb(255.0 / 255.0, 215.0 / 255.0, 0.0)),
-            TextLayout::new_with_justify(JustifyText::Center),
-            Node {
-                margin: UiRect {
-                    top: Val::Percent(33.0),
-                    ..Default::default()
-                },
-                width: Val::Percent(100.0),
-                justify_content: JustifyContent::Center,
-                align_items: AlignItems::Center,
-                position_type: PositionType::Absolute,
-                ..default()
-            },
-            Position(Vec2 { x: 0., y: 0. }),
-        ));
+    pub fn available(&self, slot: usize) -> bool {
+        self.0.get(slot).copied().flatten() != Some(0)
     }
 
-    if (current_money.0 < 0 && !level_won.0 && !level_lost.0) {
-        level_lost.0 = true;
-
-        commands.spawn((
-            Text::new("bankruptcy!"),
-            TextFont {
-                font: asset_server.load("Fonts/CyberpunkCraftpixPixel.otf"),
-                font_size: 100.,
-                ..default()
-            },
-            TextColor(Color::srgb(255.0 / 255.0, 130.0 / 255.0, 130.0 / 255.0)),
-            TextLayout::new_with_justify(JustifyText::Center),
-            BoxShadow {
-                x_offset: Val::Percent(0.),
-                y_offset: Val::Percent(0.),
-                blur_radius: Val::Percent(2.),
-                ..Default::default()
-            },
-            Node {
-                margin: UiRect {
-                    top: Val::Percent(25.0),
-                    ..Default::default()
-                },
-                width: Val::Percent(100.0),
-                justify_content: JustifyContent::Center,
-                align_items: AlignItems::Center,
-                position_type: PositionType::Absolute,
-                ..default()
-            },
-            Position(Vec2 { x: 0., y: 0. }),
-        ));
-        commands.spawn((
-            Text::new("You ran out of money \n press any key to try again"),
-            TextFont {
-                font: asset_server.load("Fonts/CyberpunkCraftpixPixel.otf"),
-                font_size: 30.,
-                ..default()
-            },
-            TextColor(Color::srgb(211.0 / 255.0, 211.0 / 255.0, 211.0 / 255.0)),
-            TextLayout::new_with_justify(JustifyText::Center),
-            Node {
-                margin: UiRect {
-                    top: Val::Percent(33.0),
-                    ..Default::default()
-                },
-                width: Val::Percent(100.0),
-                justify_content: JustifyContent::Center,
-                align_items: AlignItems::Center,
-                position_type: PositionType::Absolute,
-                ..default()
-            },
-            Position(Vec2 { x: 0., y: 0. }),
-        ));
+    pub fn take(&mut self, slot: usize) {
+        if let Some(Some(left)) = self.0.get_mut(slot) {
+            *left = left.saturating_sub(1);
+        }
     }
 
-    if (current_level.0 == 0) {
-        level_won.0 = true;
+    pub fn restock(&mut self, slot: usize) {
+        if let Some(Some(left)) = self.0.get_mut(slot) {
+            *left += 1;
+        }
     }
+}
 
-    if (level_won.0 || level_lost.0) {
-        if (keyboard_input.get_pressed().count() > 0) {
-            if (level_won.0) {
-                info!("Level completed! Loading next level...");
-                current_level.0 += 1; // Increment the level
-            }
-            level_won.0 = false;
-            level_lost.0 = false;
-            current_money.0 = 123;
-            money_goal.0 = 1234;
-            selected_tool.0 = Tools::Mouse;
-
-            // Despawn all entities from the current level
-            for entity in level_entities_query.iter() {
-                commands.entity(entity).despawn_recursive();
-            }
+// The game is either in the level select menu or in a level.
+#[derive(States, Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AppState {
+    #[default]
+    Menu,
+    InLevel,
+}
 
-            // Check if the next level exists in the registry
-            if let Some(spawn_fn) = level_registry.0.get(&current_level.0) {
-                info!("Loading Level: {}", current_level.0);
-                spawn_fn(commands, current_money, money_goal, asset_server); // Call the spawn function for the new level
-            } else {
-                // No more levels defined, handle "Game Over" or loop back to level 0
-                info!(
-                    "No more levels found. Current level: {}. Game Over!",
-                    current_level.0
-                );
+// Phases of the current level. Physics and gameplay only run while `Playing`.
+#[derive(SubStates, Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[source(AppState = AppState::InLevel)]
+pub enum LevelState {
+    #[default]
+    Loading, // replaces the previous level with the one in `CurrentLevel`, entered again to restart
+    Playing,
+    Paused,
+    Won,
+    Lost,
+}
+
+impl LevelRegistry {
+    // read while the plugin is built, so the levels are known before the first state transition
+    pub fn load() -> LevelRegistry {
+        let mut level_registry = LevelRegistry::default();
+        for result in read_level_files() {
+            match result {
+                Ok((number, level)) => {
+                    level_registry.0.insert(number, level);
+                }
+                Err(error) => error!("{}", error),
             }
         }
+        info!("Loaded {} level files", level_registry.0.len());
+        level_registry
     }
 }
 
-pub fn load_level_0(
+// everything spawned for a level, despawned by `unload_level`
+#[derive(Component, Default)]
+pub struct LevelEntity;
+
+// removes everything of the previous level
+#[allow(clippy::too_many_arguments)]
+pub fn unload_level(
     mut commands: Commands,
+    level_entities_query: Query<Entity, With<LevelEntity>>,
+    mut selected_tool: ResMut<SelectedTool>,
+    mut stats: ResMut<SimulationStats>,
+    mut rng: ResMut<SimulationRng>,
+    mut history: ResMut<EditHistory>,
+    mut placement: ResMut<MachinePlacement>,
+    mut broadphase: ResMut<Broadphase>,
+) {
+    selected_tool.0 = Tools::Mouse;
+    placement.0 = None;
+    *stats = SimulationStats::default();
+    rng.reset();
+    history.clear();
+    *broadphase = Broadphase::default();
+
+    for entity in level_entities_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn load_level(
+    commands: Commands,
+    level_registry: Res<LevelRegistry>,
+    current_level: Res<CurrentLevel>,
     mut money: ResMut<CurrentMoney>,
     mut money_goal: ResMut<MoneyGoal>,
+    mut ink_budget: ResMut<InkBudget>,
+    mut refund_percent: ResMut<RefundPercent>,
+    mut stock: ResMut<ShopStock>,
+    mut phase: ResMut<RunPhase>,
     asset_server: Res<AssetServer>,
+    mut next_state: ResMut<NextState<LevelState>>,
+    mut next_app_state: ResMut<NextState<AppState>>,
 ) {
-    money.0 = 123;
-    money_goal.0 = 1234;
+    let Some(level) = level_registry.0.get(&current_level.0) else {
+        error!("No level file registered for level {}", current_level.0);
+        next_app_state.set(AppState::Menu);
+        return;
+    };
+    info!("Loading Level: {}", current_level.0);
+    ink_budget.0 = level.ink;
+    refund_percent.0 = level.refund_percent;
+    *stock = ShopStock::from_level(level);
+    *phase = if level.build_phase {
+        RunPhase::Building
+    } else {
+        RunPhase::Free
+    };
+    spawn_level(commands, level, &mut money, &mut money_goal, &asset_server);
+    if level.explainer {
+        // nothing to play, any key continues
+        next_state.set(LevelState::Won);
+    } else {
+        next_state.set(LevelState::Playing);
+    }
+}
 
-    commands.spawn((
-        SpriteView::BackgroundCity,
-        Position(Vec2 { x: 0.0, y: 0.0 }),
-    ));
+pub fn check_level_end(
+    current_money: Res<CurrentMoney>,
+    money_goal: Res<MoneyGoal>,
+    mut next_state: ResMut<NextState<LevelState>>,
+) {
+    if current_money.0 >= money_goal.0 {
+        next_state.set(LevelState::Won);
+    } else if current_money.0 < 0 {
+        next_state.set(LevelState::Lost);
+    }
+}
+
+pub fn show_level_won(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    current_level: Res<CurrentLevel>,
+    level_registry: Res<LevelRegistry>,
+) {
+    let is_explainer = level_registry
+        .0
+        .get(&current_level.0)
+        .is_some_and(|level| level.explainer);
+    if is_explainer {
+        // the hints already say what to do
+        return;
+    }
 
-    /* explainer level */
+    let mut complete_text = Text::new("Level Complete!");
+    let mut subtext = Text::new("Press Any Key To Continue");
+    if current_level.0 == (level_registry.0.iter().count() - 1) as u32 {
+        complete_text = Text::new("You won!");
+        subtext = Text::new("please leave a rating :)");
+    }
     commands.spawn((
-        Text::new("Assembly Anarchy"),
+        complete_text,
         TextFont {
             font: asset_server.load("Fonts/CyberpunkCraftpixPixel.otf"),
             font_size: 100.,
@@ -287,7 +207,7 @@ pub fn load_level_0(
         },
         Node {
             margin: UiRect {
-                top: Val::Percent(20.0),
+                top: Val::Percent(25.0),
                 ..Default::default()
             },
             width: Val::Percent(100.0),
@@ -296,28 +216,20 @@ pub fn load_level_0(
             position_type: PositionType::Absolute,
             ..default()
         },
-        Position(Vec2 { x: 0., y: 0. }),
+        StateScoped(LevelState::Won),
     ));
-
     commands.spawn((
-        Text::new("Assemble the correct items from the given inputs to earn money \n Good luck and have fun! \n\n press any key to continue..."),
+        subtext,
         TextFont {
             font: asset_server.load("Fonts/CyberpunkCraftpixPixel.otf"),
             font_size: 30.,
             ..default()
         },
-        TextColor(Color::srgb(211.0 / 255.0, 211.0 / 255.0, 211.0 / 255.0)),
+        TextColor(Color::srgb(255.0 / 255.0, 215.0 / 255.0, 0.0)),
         TextLayout::new_with_justify(JustifyText::Center),
-        BoxShadow {
-            x_offset: Val::Percent(0.),
-            y_offset: Val::Percent(0.),
-            blur_radius: Val::Percent(5.),
-            spread_radius: Val::Percent(100.),
-            ..Default::default()
-        },
         Node {
             margin: UiRect {
-                top: Val::Percent(30.0),
+                top: Val::Percent(33.0),
                 ..Default::default()
             },
             width: Val::Percent(100.0),
@@ -326,473 +238,302 @@ pub fn load_level_0(
             position_type: PositionType::Absolute,
             ..default()
         },
-        Position(Vec2 { x: 0., y: 0. }),
+        StateScoped(LevelState::Won),
     ));
 }
 
-pub fn load_level_1(
-    mut commands: Commands,
-    mut money: ResMut<CurrentMoney>,
-    mut money_goal: ResMut<MoneyGoal>,
-    asset_server: Res<AssetServer>,
-) {
-    money.0 = 100;
-    money_goal.0 = 120;
-
+pub fn show_level_lost(mut commands: Commands, asset_server: Res<AssetServer>) {
     commands.spawn((
-        SpriteView::InputPipe,
-        Position(INPUT_PIPE_POS3),
-        Clickable,
-        InputPipe {
-            item: Item::Bolt,
-            spawn_rate: 5.,
-            time_elapsed: 0.,
-            enabled: false,
-            cost: 1,
-        },
-    ));
-
-    commands.spawn((
-        SpriteView::OutputPipe,
-        OutputPipe {
-            item: Item::Bolt,
-            reward: 5,
+        Text::new("bankruptcy!"),
+        TextFont {
+            font: asset_server.load("Fonts/CyberpunkCraftpixPixel.otf"),
+            font_size: 100.,
+            ..default()
         },
-        Position(OUTPUT_PIPE_POS3),
-    ));
-
-    commands.spawn((
-        SpriteView::BackgroundIndustry,
-        Position(Vec2 { x: 0.0, y: 0.0 }),
-    ));
-
-    // user click indicator (mini tutorial)
-    commands.spawn((
-        SpriteView::CursorPointLeft,
-        Position(Vec2 {
-            x: INPUT_PIPE_POS3.x + 125.,
-            y: INPUT_PIPE_POS3.y,
-        }),
-    ));
-}
-
-pub fn load_level_2(
-    mut commands: Commands,
-    mut money: ResMut<CurrentMoney>,
-    mut money_goal: ResMut<MoneyGoal>,
-    asset_server: Res<AssetServer>,
-) {
-    money.0 = 100;
-    money_goal.0 = 120;
-
-    setup_mouse_button(commands.reborrow());
-    setup_draw_button(commands.reborrow());
-
-    commands.spawn((
-        SpriteView::InputPipe,
-        Position(INPUT_PIPE_POS3),
-        Clickable,
-        InputPipe {
-            item: Item::Bolt,
-            spawn_rate: 5.,
-            time_elapsed: 0.,
-            enabled: false,
-            cost: 1,
+        TextColor(Color::srgb(255.0 / 255.0, 130.0 / 255.0, 130.0 / 255.0)),
+        TextLayout::new_with_justify(JustifyText::Center),
+        BoxShadow {
+            x_offset: Val::Percent(0.),
+            y_offset: Val::Percent(0.),
+            blur_radius: Val::Percent(2.),
+            ..Default::default()
         },
-    ));
-
-    commands.spawn((
-        SpriteView::OutputPipe,
-        OutputPipe {
-            item: Item::Bolt,
-            reward: 5,
+        Node {
+            margin: UiRect {
+                top: Val::Percent(25.0),
+                ..Default::default()
+            },
+            width: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            position_type: PositionType::Absolute,
+            ..default()
         },
-        Position(OUTPUT_PIPE_POS5),
+        StateScoped(LevelState::Lost),
     ));
-
-    commands.spawn((
-        SpriteView::BackgroundIndustry2,
-        Position(Vec2 { x: 0.0, y: 0.0 }),
-    ));
-
-    // user click indicator (mini tutorial)
     commands.spawn((
-        SpriteView::CursorPointLeft,
-        Position(Vec2 {
-            x: BUTTON_DRAW_POS.x + 100.,
-            y: BUTTON_DRAW_POS.y,
-        }),
-    ));
-}
-
-pub fn load_level_3(
-    mut commands: Commands,
-    mut money: ResMut<CurrentMoney>,
-    mut money_goal: ResMut<MoneyGoal>,
-    asset_server: Res<AssetServer>,
-) {
-    money.0 = 200;
-    money_goal.0 = 300;
-
-    setup_mouse_button(commands.reborrow());
-    setup_draw_button(commands.reborrow());
-
-    commands.spawn((
-        SpriteView::InputPipe,
-        Position(INPUT_PIPE_POS3),
-        Clickable,
-        InputPipe {
-            item: Item::Steel,
-            spawn_rate: 5.,
-            time_elapsed: 0.,
-            enabled: false,
-            cost: 2,
-        },
-    ));
-
-    spawn_machine_window(
-        Position(MACHINE_BUTTON_1),
-        Machine::OneToOneCrafter {
-            input: Item::Steel,
-            output: Item::Bolt,
-            cost: 100,
+        Text::new("You ran out of money \n press any key to try again"),
+        TextFont {
+            font: asset_server.load("Fonts/CyberpunkCraftpixPixel.otf"),
+            font_size: 30.,
+            ..default()
         },
-        commands.reborrow(),
-        &asset_server,
-    );
-
-    commands.spawn((
-        SpriteView::OutputPipe,
-        OutputPipe {
-            item: Item::Bolt,
-            reward: 10,
+        TextColor(Color::srgb(211.0 / 255.0, 211.0 / 255.0, 211.0 / 255.0)),
+        TextLayout::new_with_justify(JustifyText::Center),
+        Node {
+            margin: UiRect {
+                top: Val::Percent(33.0),
+                ..Default::default()
+            },
+            width: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            position_type: PositionType::Absolute,
+            ..default()
         },
-        Position(OUTPUT_PIPE_POS3),
-    ));
-
-    commands.spawn((
-        SpriteView::BackgroundIndustry,
-        Position(Vec2 { x: 0.0, y: 0.0 }),
-    ));
-
-    // user click indicator (mini tutorial)
-    commands.spawn((
-        SpriteView::CursorPointRight,
-        Position(Vec2 {
-            x: MACHINE_BUTTON_1.x + -210.,
-            y: MACHINE_BUTTON_1.y,
-        }),
+        StateScoped(LevelState::Lost),
     ));
 }
 
-pub fn load_level_4(
-    mut commands: Commands,
-    mut money: ResMut<CurrentMoney>,
-    mut money_goal: ResMut<MoneyGoal>,
-    asset_server: Res<AssetServer>,
+// any key continues with the next level, or retries a lost one. escape goes back to the level select menu
+pub fn continue_after_level_end(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    level_state: Res<State<LevelState>>,
+    mut current_level: ResMut<CurrentLevel>,
+    level_registry: Res<LevelRegistry>,
+    mut next_level_state: ResMut<NextState<LevelState>>,
+    mut next_app_state: ResMut<NextState<AppState>>,
 ) {
-    money.0 = 200;
-    money_goal.0 = 300;
-
-    setup_mouse_button(commands.reborrow());
-    setup_draw_button(commands.reborrow());
-
-    commands.spawn((
-        SpriteView::InputPipe,
-        Position(INPUT_PIPE_POS4),
-        Clickable,
-        InputPipe {
-            item: Item::Iron,
-            spawn_rate: 15.,
-            time_elapsed: 0.,
-            enabled: false,
-            cost: 2,
-        },
-    ));
-
-    spawn_machine_window(
-        Position(MACHINE_BUTTON_1),
-        Machine::OneToOneCrafter {
-            input: Item::Iron,
-            output: Item::Wrench,
-            cost: 100,
-        },
-        commands.reborrow(),
-        &asset_server,
-    );
-
-    commands.spawn((
-        SpriteView::OutputPipe,
-        OutputPipe {
-            item: Item::Wrench,
-            reward: 10,
-        },
-        Position(OUTPUT_PIPE_POS1),
-    ));
-
-    commands.spawn((
-        SpriteView::BackgroundCity,
-        Position(Vec2 { x: 0.0, y: 0.0 }),
-    ));
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        next_app_state.set(AppState::Menu);
+        return;
+    }
+    if keyboard_input.get_just_pressed().next().is_none() {
+        return;
+    }
+    if *level_state.get() == LevelState::Won {
+        if !level_registry.0.contains_key(&(current_level.0 + 1)) {
+            info!(
+                "No more levels found. Current level: {}. Game Over!",
+                current_level.0
+            );
+            next_app_state.set(AppState::Menu);
+            return;
+        }
+        info!("Level completed! Loading next level...");
+        current_level.0 += 1;
+    }
+    next_level_state.set(LevelState::Loading);
 }
 
-pub fn load_level_5(
-    mut commands: Commands,
-    mut money: ResMut<CurrentMoney>,
-    mut money_goal: ResMut<MoneyGoal>,
-    asset_server: Res<AssetServer>,
+// escape pauses and resumes the level, m leaves a paused level for the level select menu
+pub fn pause_level(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    level_state: Res<State<LevelState>>,
+    mut next_level_state: ResMut<NextState<LevelState>>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+    placement: Res<MachinePlacement>,
 ) {
-    money.0 = 5000;
-    money_goal.0 = 5500;
-
-    setup_mouse_button(commands.reborrow());
-    setup_draw_button(commands.reborrow());
-
-    commands.spawn((
-        SpriteView::InputPipe,
-        Position(INPUT_PIPE_POS2),
-        Clickable,
-        InputPipe {
-            item: Item::Gold,
-            spawn_rate: 3.,
-            time_elapsed: 0.,
-            enabled: false,
-            cost: 200,
-        },
-    ));
-
-    spawn_machine_window(
-        Position(MACHINE_BUTTON_1),
-        Machine::OneToOneCrafter {
-            input: Item::Gold,
-            output: Item::Jewelry,
-            cost: 200,
-        },
-        commands.reborrow(),
-        &asset_server,
-    );
-
-    commands.spawn((
-        SpriteView::OutputPipe,
-        OutputPipe {
-            item: Item::Jewelry,
-            reward: 250,
-        },
-        Position(OUTPUT_PIPE_POS3),
-    ));
-
-    commands.spawn((
-        SpriteView::BackgroundCity2,
-        Position(Vec2 { x: 0.0, y: 0.0 }),
-    ));
+    let paused = *level_state.get() == LevelState::Paused;
+    // Escape cancels placing a machine first, see `cancel_placement`
+    if keyboard_input.just_pressed(KeyCode::Escape) && placement.0.is_none() {
+        next_level_state.set(if paused {
+            LevelState::Playing
+        } else {
+            LevelState::Paused
+        });
+    } else if paused && keyboard_input.just_pressed(KeyCode::KeyM) {
+        next_app_state.set(AppState::Menu);
+    }
 }
 
-pub fn load_level_6(
-    mut commands: Commands,
-    mut money: ResMut<CurrentMoney>,
-    mut money_goal: ResMut<MoneyGoal>,
-    asset_server: Res<AssetServer>,
-) {
-    money.0 = 1000;
-    money_goal.0 = 2000;
-
-    setup_mouse_button(commands.reborrow());
-    setup_draw_button(commands.reborrow());
-
+pub fn show_paused(mut commands: Commands, asset_server: Res<AssetServer>) {
     commands.spawn((
-        SpriteView::InputPipe,
-        Position(INPUT_PIPE_POS3),
-        Clickable,
-        InputPipe {
-            item: Item::Crystal,
-            spawn_rate: 30.,
-            time_elapsed: 0.,
-            enabled: false,
-            cost: 10,
+        Text::new("paused \n esc to resume - m for level select"),
+        TextFont {
+            font: asset_server.load("Fonts/CyberpunkCraftpixPixel.otf"),
+            font_size: 30.,
+            ..default()
         },
-    ));
-
-    spawn_machine_window(
-        Position(MACHINE_BUTTON_1),
-        Machine::Duplicator { cost: 200 },
-        commands.reborrow(),
-        &asset_server,
-    );
-
-    commands.spawn((
-        SpriteView::OutputPipe,
-        OutputPipe {
-            item: Item::Crystal,
-            reward: 9,
+        TextColor(Color::srgb(211.0 / 255.0, 211.0 / 255.0, 211.0 / 255.0)),
+        TextLayout::new_with_justify(JustifyText::Center),
+        Node {
+            margin: UiRect {
+                top: Val::Percent(25.0),
+                ..Default::default()
+            },
+            width: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            position_type: PositionType::Absolute,
+            ..default()
         },
-        Position(OUTPUT_PIPE_POS3),
-    ));
-
-    commands.spawn((
-        SpriteView::BackgroundIndustry,
-        Position(Vec2 { x: 0.0, y: 0.0 }),
+        StateScoped(LevelState::Paused),
     ));
 }
 
-pub fn load_level_7(
+pub fn spawn_level(
     mut commands: Commands,
-    mut money: ResMut<CurrentMoney>,
-    mut money_goal: ResMut<MoneyGoal>,
-    asset_server: Res<AssetServer>,
+    level: &LevelDefinition,
+    money: &mut CurrentMoney,
+    money_goal: &mut MoneyGoal,
+    asset_server: &Res<AssetServer>,
 ) {
-    money.0 = 1000;
-    money_goal.0 = 1001;
+    money.0 = level.starting_money;
+    money_goal.0 = level.money_goal;
 
-    setup_mouse_button(commands.reborrow());
-    setup_draw_button(commands.reborrow());
-
-    commands.spawn((
-        SpriteView::InputPipe,
-        Position(INPUT_PIPE_POS3),
-        Clickable,
-        InputPipe {
-            item: Item::Jewelry,
-            spawn_rate: 50.,
-            time_elapsed: 0.,
-            enabled: false,
-            cost: 10,
-        },
-    ));
-
-    spawn_machine_window(
-        Position(MACHINE_BUTTON_1),
-        Machine::Duplicator { cost: 200 },
-        commands.reborrow(),
-        &asset_server,
-    );
-
-    commands.spawn((
-        SpriteView::OutputPipe,
-        OutputPipe {
-            item: Item::Jewelry,
-            reward: 3,
-        },
-        Position(OUTPUT_PIPE_POS3),
-    ));
-
-    commands.spawn((
-        SpriteView::BackgroundCity2,
-        Position(Vec2 { x: 0.0, y: 0.0 }),
-    ));
-}
+    if !level.explainer {
+        spawn_time_scale_buttons(commands.reborrow(), asset_server);
+    }
+    if level.build_phase {
+        spawn_run_button(commands.reborrow(), asset_server);
+    }
 
-pub fn load_level_8(
-    mut commands: Commands,
-    mut money: ResMut<CurrentMoney>,
-    mut money_goal: ResMut<MoneyGoal>,
-    asset_server: Res<AssetServer>,
-) {
-    money.0 = 2000;
-    money_goal.0 = 4000;
+    if level.tool_buttons {
+        setup_mouse_button(commands.reborrow());
+        setup_draw_button(commands.reborrow());
+        setup_erase_button(commands.reborrow());
+        setup_line_button(commands.reborrow());
+        setup_rectangle_button(commands.reborrow());
+        setup_funnel_button(commands.reborrow());
+    }
 
-    setup_mouse_button(commands.reborrow());
-    setup_draw_button(commands.reborrow());
+    for pipe in &level.input_pipes {
+        let Some(position) = input_pipe_position(pipe.slot) else {
+            continue;
+        };
+        commands.spawn((
+            SpriteView::InputPipe,
+            Position(position),
+            Clickable,
+            InputPipe {
+                item: pipe.item,
+                spawn_rate: pipe.spawn_rate,
+                time_elapsed: 0.,
+                enabled: false,
+                cost: pipe.cost,
+            },
+            LevelEntity,
+        ));
+    }
 
-    commands.spawn((
-        SpriteView::InputPipe,
-        Position(INPUT_PIPE_POS3),
-        Clickable,
-        InputPipe {
-            item: Item::Iron,
-            spawn_rate: 25.,
-            time_elapsed: 0.,
-            enabled: false,
-            cost: 20,
-        },
-    ));
+    if !level.machine_shop.is_empty() {
+        spawn_shop(commands.reborrow(), &level.machine_shop, asset_server);
+    }
 
-    spawn_machine_window(
-        Position(MACHINE_BUTTON_1),
-        Machine::Duplicator { cost: 200 },
-        commands.reborrow(),
-        &asset_server,
-    );
-
-    spawn_machine_window(
-        Position(MACHINE_BUTTON_2),
-        Machine::OneToOneCrafter {
-            input: Item::Iron,
-            output: Item::Wrench,
-            cost: 100,
-        },
-        commands.reborrow(),
-        &asset_server,
-    );
+    for pipe in &level.output_pipes {
+        let Some(position) = output_pipe_position(pipe.slot) else {
+            continue;
+        };
+        commands.spawn((
+            SpriteView::OutputPipe,
+            OutputPipe {
+                item: pipe.item,
+                reward: pipe.reward,
+                wrong_items: pipe.wrong_items,
+                quality: 1.0,
+            },
+            Position(position),
+            LevelEntity,
+        ));
+    }
 
-    commands.spawn((
-        SpriteView::OutputPipe,
-        OutputPipe {
-            item: Item::Wrench,
-            reward: 15,
-        },
-        Position(OUTPUT_PIPE_POS3),
-    ));
+    for conveyor in &level.conveyors {
+        commands.spawn(conveyor_bundle(
+            Position(Vec2::new(conveyor.x, conveyor.y)),
+            conveyor.direction,
+            conveyor.speed,
+        ));
+    }
 
     commands.spawn((
-        SpriteView::BackgroundIndustry2,
+        level.background.get_sprite_view(),
         Position(Vec2 { x: 0.0, y: 0.0 }),
+        LevelEntity,
     ));
-}
-
-pub fn load_level_9(
-    mut commands: Commands,
-    mut money: ResMut<CurrentMoney>,
-    mut money_goal: ResMut<MoneyGoal>,
-    asset_server: Res<AssetServer>,
-) {
-    money.0 = 2000;
-    money_goal.0 = 190;
-
-    setup_mouse_button(commands.reborrow());
-    setup_draw_button(commands.reborrow());
-
-    commands.spawn((
-        SpriteView::InputPipe,
-        Position(INPUT_PIPE_POS3),
-        Clickable,
-        InputPipe {
-            item: Item::Gold,
-            spawn_rate: 3.,
-            time_elapsed: 0.,
-            enabled: false,
-            cost: 200,
-        },
-    ));
-
-    spawn_machine_window(
-        Position(MACHINE_BUTTON_1),
-        Machine::Duplicator { cost: 200 },
-        commands.reborrow(),
-        &asset_server,
-    );
-
-    spawn_machine_window(
-        Position(MACHINE_BUTTON_2),
-        Machine::OneToOneCrafter {
-            input: Item::Gold,
-            output: Item::Jewelry,
-            cost: 100,
-        },
-        commands.reborrow(),
-        &asset_server,
-    );
 
-    commands.spawn((
-        SpriteView::OutputPipe,
-        OutputPipe {
-            item: Item::Jewelry,
-            reward: 55,
-        },
-        Position(OUTPUT_PIPE_POS3),
-    ));
+    for hint in &level.hints {
+        spawn_hint(commands.reborrow(), hint, asset_server);
+    }
+}
 
-    commands.spawn((
-        SpriteView::BackgroundIndustry2,
-        Position(Vec2 { x: 0.0, y: 0.0 }),
-    ));
+// user click indicators and explainer texts (mini tutorial)
+fn spawn_hint(mut commands: Commands, hint: &Hint, asset_server: &Res<AssetServer>) {
+    match hint {
+        Hint::PointLeft { x, y } => {
+            commands.spawn((
+                SpriteView::CursorPointLeft,
+                Position(Vec2 { x: *x, y: *y }),
+                LevelEntity,
+            ));
+        }
+        Hint::PointRight { x, y } => {
+            commands.spawn((
+                SpriteView::CursorPointRight,
+                Position(Vec2 { x: *x, y: *y }),
+                LevelEntity,
+            ));
+        }
+        Hint::Title(title) => {
+            commands.spawn((
+                Text::new(title.clone()),
+                TextFont {
+                    font: asset_server.load("Fonts/CyberpunkCraftpixPixel.otf"),
+                    font_size: 100.,
+                    ..default()
+                },
+                TextColor(Color::srgb(255.0 / 255.0, 215.0 / 255.0, 0.0)),
+                TextLayout::new_with_justify(JustifyText::Center),
+                BoxShadow {
+                    x_offset: Val::Percent(0.),
+                    y_offset: Val::Percent(0.),
+                    blur_radius: Val::Percent(2.),
+                    ..Default::default()
+                },
+                Node {
+                    margin: UiRect {
+                        top: Val::Percent(20.0),
+                        ..Default::default()
+                    },
+                    width: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    position_type: PositionType::Absolute,
+                    ..default()
+                },
+                LevelEntity,
+            ));
+        }
+        Hint::Subtitle(subtitle) => {
+            commands.spawn((
+                Text::new(subtitle.clone()),
+                TextFont {
+                    font: asset_server.load("Fonts/CyberpunkCraftpixPixel.otf"),
+                    font_size: 30.,
+                    ..default()
+                },
+                TextColor(Color::srgb(211.0 / 255.0, 211.0 / 255.0, 211.0 / 255.0)),
+                TextLayout::new_with_justify(JustifyText::Center),
+                BoxShadow {
+                    x_offset: Val::Percent(0.),
+                    y_offset: Val::Percent(0.),
+                    blur_radius: Val::Percent(5.),
+                    spread_radius: Val::Percent(100.),
+                    ..Default::default()
+                },
+                Node {
+                    margin: UiRect {
+                        top: Val::Percent(30.0),
+                        ..Default::default()
+                    },
+                    width: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    position_type: PositionType::Absolute,
+                    ..default()
+                },
+                LevelEntity,
+            ));
+        }
+    }
 }
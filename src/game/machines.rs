@@ -1,19 +1,108 @@
 use crate::game::*;
+use bevy::sprite::Anchor;
 use bevy::state::commands;
 use rand::Rng;
+use serde::Deserialize;
+use std::collections::VecDeque;
 
-#[derive(Component, Clone, Copy, PartialEq)]
+#[derive(Component, Debug, Clone, Copy, PartialEq, Deserialize)]
 pub enum Machine {
     OneToOneCrafter {
         input: Item,
         output: Item,
         cost: i32,
     },
+    TwoToOneCrafter {
+        inputs: [Item; 2],
+        output: Item,
+        cost: i32,
+    },
     Duplicator {
         cost: i32,
     },
+    Sorter {
+        item: Item,
+        cost: i32,
+    },
+    Conveyor {
+        direction: BeltDirection,
+        speed: f32,
+        cost: i32,
+    },
+}
+
+// the way items on top of a conveyor are carried
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum BeltDirection {
+    Left,
+    Right,
+}
+
+impl BeltDirection {
+    pub fn get_name(&self) -> &str {
+        match self {
+            BeltDirection::Left => "left",
+            BeltDirection::Right => "right",
+        }
+    }
+
+    // sign of the surface velocity
+    pub fn get_sign(&self) -> f32 {
+        match self {
+            BeltDirection::Left => -1.0,
+            BeltDirection::Right => 1.0,
+        }
+    }
+}
+
+// ingredients a two to one crafter is holding on to, one slot per entry in its `inputs`
+#[derive(Component, Default)]
+pub struct IngredientBuffer(pub [bool; 2]);
+
+// items a machine can't take, e.g. with a full queue, get sent back up this fast
+const BOUNCE_SPEED: f32 = 250.0;
+
+// sends an item back up, as seen from the turned machine
+fn bounce_off(rotation: &Rotation, velocity: &mut Velocity) {
+    let mut local_velocity = rotation.rot2().inverse() * velocity.0;
+    local_velocity.y = BOUNCE_SPEED;
+    velocity.0 = rotation.rot2() * local_velocity;
+}
+
+// products a machine has accepted the inputs for, the front one is being worked on
+#[derive(Component, Default)]
+pub struct ProcessingQueue {
+    pub products: VecDeque<Item>,
+    pub progress: f32, // seconds spent on the front product
+}
+
+impl ProcessingQueue {
+    pub fn is_full(&self, machine: &Machine) -> bool {
+        self.products.len() >= machine.get_queue_capacity()
+    }
+
+    // works on the front product, returns it once it is done
+    pub fn process(&mut self, delta_secs: f32, processing_time: f32) -> Option<Item> {
+        if self.products.is_empty() {
+            return None;
+        }
+        self.progress += delta_secs;
+        if self.progress < processing_time {
+            return None;
+        }
+        self.progress = 0.0;
+        self.products.pop_front()
+    }
 }
 
+// bar on a machine showing how far the current product is
+#[derive(Component)]
+pub struct ProgressBar {
+    machine: Entity,
+}
+
+const PROGRESS_BAR_SIZE: Vec2 = Vec2::new(80.0, 8.0);
+
 #[derive(Component)]
 pub struct FollowWithOffset {
     relative_pos: Position,
@@ -28,7 +117,17 @@ impl Machine {
                 output,
                 cost,
             } => format!("crafts {} into {}", input.get_name(), output.get_name()),
+            Machine::TwoToOneCrafter { inputs, output, .. } => format!(
+                "crafts {} and {} into {}",
+                inputs[0].get_name(),
+                inputs[1].get_name(),
+                output.get_name()
+            ),
             Machine::Duplicator { cost } => "duplicates input".to_string(),
+            Machine::Sorter { item, .. } => format!("sorts {} to the left", item.get_name()),
+            Machine::Conveyor { direction, .. } => {
+                format!("carries items {}", direction.get_name())
+            }
         }
     }
 
@@ -39,7 +138,10 @@ impl Machine {
                 output,
                 cost,
             } => "Crafter".to_string(),
+            Machine::TwoToOneCrafter { .. } => "Assembler".to_string(),
             Machine::Duplicator { cost } => "Duplicator".to_string(),
+            Machine::Sorter { .. } => "Sorter".to_string(),
+            Machine::Conveyor { .. } => "Conveyor".to_string(),
         }
     }
 
@@ -50,7 +152,35 @@ impl Machine {
                 output,
                 cost,
             } => *cost,
+            Machine::TwoToOneCrafter { cost, .. } => *cost,
             Machine::Duplicator { cost } => *cost,
+            Machine::Sorter { cost, .. } => *cost,
+            Machine::Conveyor { cost, .. } => *cost,
+        }
+    }
+
+    // what selling the machine pays back, rounded down
+    pub fn get_refund(&self, percent: u32) -> i32 {
+        self.get_cost() * percent as i32 / 100
+    }
+
+    // seconds of work per product
+    pub fn get_processing_time(&self) -> f32 {
+        match self {
+            Machine::OneToOneCrafter { .. } => 0.1,
+            Machine::TwoToOneCrafter { .. } => 0.2,
+            Machine::Duplicator { .. } => 0.1,
+            Machine::Sorter { .. } | Machine::Conveyor { .. } => 0.0,
+        }
+    }
+
+    // products that can wait in the queue, further inputs bounce off
+    pub fn get_queue_capacity(&self) -> usize {
+        match self {
+            Machine::OneToOneCrafter { .. } => 4,
+            Machine::TwoToOneCrafter { .. } => 2,
+            Machine::Duplicator { .. } => 4,
+            Machine::Sorter { .. } | Machine::Conveyor { .. } => 0,
         }
     }
 
@@ -61,22 +191,44 @@ impl Machine {
                 output,
                 cost,
             } => SpriteView::OneToOneCrafter,
+            Machine::TwoToOneCrafter { .. } => SpriteView::TwoToOneCrafter,
             Machine::Duplicator { cost } => SpriteView::Duplicator,
+            Machine::Sorter { .. } => SpriteView::Sorter,
+            Machine::Conveyor { direction, .. } => SpriteView::Conveyor {
+                direction: *direction,
+            },
         }
     }
 }
 
+// followers turn with the entity they follow, the offset as well
 pub fn follow_relative_position(
     mut commands: Commands,
-    mut followers: Query<(Entity, &mut Position, &FollowWithOffset), With<FollowWithOffset>>,
-    all_objects: Query<(&Position), Without<FollowWithOffset>>,
+    mut followers: Query<
+        (
+            Entity,
+            &mut Position,
+            &FollowWithOffset,
+            Option<&mut Rotation>,
+        ),
+        With<FollowWithOffset>,
+    >,
+    all_objects: Query<(&Position, Option<&Rotation>), Without<FollowWithOffset>>,
 ) {
-    for (entity, mut pos, follow) in followers.iter_mut() {
-        if let Ok(follow_pos) = all_objects.get(follow.entity) {
-            pos.0 = Vec2 {
-                x: follow_pos.0.x + follow.relative_pos.0.x,
-                y: follow_pos.0.y + follow.relative_pos.0.y,
-            };
+    for (entity, mut pos, follow, rotation) in followers.iter_mut() {
+        if let Ok((follow_pos, follow_rotation)) = all_objects.get(follow.entity) {
+            let follow_rotation = follow_rotation.copied().unwrap_or_default();
+            pos.0 = follow_pos.0 + follow_rotation.rot2() * follow.relative_pos.0;
+            match rotation {
+                Some(mut rotation) => {
+                    if *rotation != follow_rotation {
+                        *rotation = follow_rotation;
+                    }
+                }
+                None => {
+                    commands.entity(entity).insert(follow_rotation);
+                }
+            }
         } else {
             // parent no longer exists
             commands.entity(entity).despawn();
@@ -84,7 +236,87 @@ pub fn follow_relative_position(
     }
 }
 
-pub fn spawn_one_to_one_crafter(mut commands: Commands, position: Position, crafter: Machine) {
+// removes a machine together with its icons and progress bar
+pub fn despawn_with_followers(
+    mut commands: Commands,
+    entity: Entity,
+    followers: &Query<(Entity, &FollowWithOffset)>,
+) {
+    for (follower, follow) in followers.iter() {
+        if follow.entity == entity {
+            commands.entity(follower).despawn();
+        }
+    }
+    commands.entity(entity).despawn();
+}
+
+// the bar fills up from the left, centered on the machine
+fn spawn_progress_bar(mut commands: Commands, machine_entity: Entity) {
+    let mut sprite = Sprite::from_color(Color::srgb(1.0, 215.0 / 255.0, 0.0), PROGRESS_BAR_SIZE);
+    sprite.anchor = Anchor::CenterLeft;
+    commands.spawn((
+        sprite,
+        Transform::from_xyz(10000., 10000., 100.),
+        Position(Vec2 { x: 0.0, y: 0.0 }),
+        FollowWithOffset {
+            relative_pos: Position(Vec2 {
+                x: -PROGRESS_BAR_SIZE.x / 2.0,
+                y: 0.0,
+            }),
+            entity: machine_entity,
+        },
+        ProgressBar {
+            machine: machine_entity,
+        },
+        LevelEntity,
+    ));
+}
+
+pub fn update_progress_bars(
+    mut bars: Query<(
+        &ProgressBar,
+        &Position,
+        Option<&Rotation>,
+        &mut Sprite,
+        &mut Transform,
+    )>,
+    machines: Query<(&Machine, &ProcessingQueue)>,
+) {
+    for (bar, pos, rotation, mut sprite, mut transform) in bars.iter_mut() {
+        let Ok((machine, queue)) = machines.get(bar.machine) else {
+            continue;
+        };
+        let progress = if queue.products.is_empty() {
+            0.0
+        } else {
+            (queue.progress / machine.get_processing_time()).min(1.0)
+        };
+        sprite.custom_size = Some(Vec2::new(
+            progress * PROGRESS_BAR_SIZE.x,
+            PROGRESS_BAR_SIZE.y,
+        ));
+        // not a sprite view, so it has to be moved along by hand
+        transform.translation.x = pos.0.x;
+        transform.translation.y = pos.0.y;
+        transform.rotation = Quat::from_rotation_z(rotation.map_or(0.0, |r| r.0));
+    }
+}
+
+pub fn spawn_machine(commands: Commands, position: Position, machine: Machine) -> Entity {
+    match machine {
+        Machine::OneToOneCrafter { .. } => spawn_one_to_one_crafter(commands, position, machine),
+        Machine::TwoToOneCrafter { .. } => spawn_two_to_one_crafter(commands, position, machine),
+        Machine::Duplicator { .. } => spawn_duplicator(commands, position, machine),
+        Machine::Sorter { .. } => spawn_sorter(commands, position, machine),
+        Machine::Conveyor { .. } => spawn_conveyor(commands, position, machine),
+    }
+}
+
+pub fn spawn_one_to_one_crafter(
+    mut commands: Commands,
+    position: Position,
+    crafter: Machine,
+) -> Entity {
     match crafter {
         Machine::OneToOneCrafter {
             input,
@@ -107,16 +339,20 @@ pub fn spawn_one_to_one_crafter(mut commands: Commands, position: Position, craf
                     offset_y: 0.0,
                 },
             ];
-            let mut crafter_entity = commands
+            let crafter_entity = commands
                 .spawn((
                     crafter.get_sprite_view(),
                     position,
+                    Rotation::default(),
                     ColliderCollection(physics_colliders),
                     crafter,
+                    ProcessingQueue::default(),
                     Clickable,
                     DragAble,
+                    LevelEntity,
                 ))
                 .id();
+            spawn_progress_bar(commands.reborrow(), crafter_entity);
 
             // add item icons
             commands.spawn((
@@ -132,6 +368,7 @@ pub fn spawn_one_to_one_crafter(mut commands: Commands, position: Position, craf
                     }),
                     entity: crafter_entity,
                 },
+                LevelEntity,
             ));
             commands.spawn((
                 SpriteView::Item {
@@ -146,61 +383,262 @@ pub fn spawn_one_to_one_crafter(mut commands: Commands, position: Position, craf
                     }),
                     entity: crafter_entity,
                 },
+                LevelEntity,
             ));
+            crafter_entity
         }
-        _ => info!("Wrong machine type given"),
+        _ => panic!("Wrong machine type given"),
     }
 }
 
 pub fn one_to_one_crafter(
     mut commands: Commands,
-    items: Query<(Entity, &Item, &Position), With<Item>>,
-    crafter: Query<(&Machine, &Position), Without<Item>>,
+    mut items: Query<(Entity, &Item, &Position, &mut Velocity), With<Item>>,
+    mut rng: ResMut<SimulationRng>,
+    time: Res<Time>,
+    mut crafter: Query<(&Machine, &Position, &Rotation, &mut ProcessingQueue), Without<Item>>,
 ) {
-    for (machine, machine_pos) in crafter.iter() {
+    for (machine, machine_pos, rotation, mut queue) in crafter.iter_mut() {
         match machine {
             Machine::OneToOneCrafter {
                 input,
                 output,
                 cost,
             } => {
-                for (item_entity, item, item_pos) in items.iter() {
-                    if (machine_pos.0.distance(item_pos.0)
-                        < 0.5625 * machine.get_sprite_view().get_scale().x / 2.0)
+                for (item_entity, item, item_pos, mut item_vel) in items.iter_mut() {
+                    if machine_pos.0.distance(item_pos.0)
+                        < 0.5625 * machine.get_sprite_view().get_scale().x / 2.0
                     {
                         // item inside machine
-                        if (*item == *input) {
-                            // spawn product
-                            let mut rng = rand::rng();
-                            let random_velocity_x = rng.random_range(-50.0..50.0);
-                            commands.spawn((
-                                SpriteView::Item {
-                                    item: output.clone(),
-                                    ui_element: false,
-                                },
-                                output.clone(),
-                                Position(Vec2 {
-                                    x: machine_pos.0.x,
-                                    y: machine_pos.0.y
-                                        - machine.get_sprite_view().get_scale().y * 0.6 / 2.0,
-                                }),
-                                CirclePhysics { radius: 14.0 },
-                                Velocity(Vec2 {
-                                    x: random_velocity_x,
-                                    y: 0.,
-                                }),
-                            ));
+                        if *item == *input && queue.is_full(machine) {
+                            bounce_off(rotation, &mut item_vel);
+                            continue;
+                        }
+                        if *item == *input {
+                            queue.products.push_back(*output);
                         }
                         commands.entity(item_entity).despawn();
                     }
                 }
+
+                if let Some(product) =
+                    queue.process(time.delta_secs(), machine.get_processing_time())
+                {
+                    // spawn product
+                    let random_velocity_x = rng.random_range(-50.0..50.0);
+                    commands.spawn((
+                        SpriteView::Item {
+                            item: product,
+                            ui_element: false,
+                        },
+                        product,
+                        Position(
+                            machine_pos.0
+                                + rotation.rot2()
+                                    * Vec2 {
+                                        x: 0.0,
+                                        // well clear of the intake, so queued items can't push the product back in
+                                        y: -machine.get_sprite_view().get_scale().y * 0.8 / 2.0,
+                                    },
+                        ),
+                        CirclePhysics { radius: 14.0 },
+                        Velocity(
+                            rotation.rot2()
+                                * Vec2 {
+                                    x: random_velocity_x,
+                                    y: 0.,
+                                },
+                        ),
+                        LevelEntity,
+                    ));
+                }
             }
             _ => (),
         }
     }
 }
 
-pub fn spawn_duplicator(mut commands: Commands, position: Position, duplicator: Machine) {
+// two intakes on top, left and right of a divider. items rest on the floor until there is room in the buffer
+pub fn spawn_two_to_one_crafter(
+    mut commands: Commands,
+    position: Position,
+    crafter: Machine,
+) -> Entity {
+    match crafter {
+        Machine::TwoToOneCrafter { inputs, output, .. } => {
+            let crafter_width = crafter.get_sprite_view().get_scale().x;
+            let crafter_height = crafter.get_sprite_view().get_scale().y;
+            let physics_colliders = vec![
+                RectanglePhysics {
+                    width: 0.06 * crafter_width,
+                    height: 0.7 * crafter_height,
+                    offset_x: -0.4 * crafter_width,
+                    offset_y: 0.0,
+                },
+                RectanglePhysics {
+                    width: 0.06 * crafter_width,
+                    height: 0.7 * crafter_height,
+                    offset_x: 0.0,
+                    offset_y: 0.0,
+                },
+                RectanglePhysics {
+                    width: 0.06 * crafter_width,
+                    height: 0.7 * crafter_height,
+                    offset_x: 0.4 * crafter_width,
+                    offset_y: 0.0,
+                },
+                // floor
+                RectanglePhysics {
+                    width: 0.86 * crafter_width,
+                    height: 0.1 * crafter_height,
+                    offset_x: 0.0,
+                    offset_y: -0.25 * crafter_height,
+                },
+            ];
+            let crafter_entity = commands
+                .spawn((
+                    crafter.get_sprite_view(),
+                    position,
+                    Rotation::default(),
+                    ColliderCollection(physics_colliders),
+                    crafter,
+                    IngredientBuffer::default(),
+                    ProcessingQueue::default(),
+                    Clickable,
+                    DragAble,
+                    LevelEntity,
+                ))
+                .id();
+            spawn_progress_bar(commands.reborrow(), crafter_entity);
+
+            // add item icons, one above each intake and the product below
+            for (input, intake) in inputs.iter().zip(two_to_one_crafter_intakes(&crafter)) {
+                commands.spawn((
+                    SpriteView::Item {
+                        item: *input,
+                        ui_element: true,
+                    },
+                    Position(Vec2 { x: 0.0, y: 0.0 }),
+                    FollowWithOffset {
+                        relative_pos: Position(Vec2 {
+                            x: intake.x,
+                            y: crafter_height * 0.3,
+                        }),
+                        entity: crafter_entity,
+                    },
+                    LevelEntity,
+                ));
+            }
+            commands.spawn((
+                SpriteView::Item {
+                    item: output,
+                    ui_element: true,
+                },
+                Position(Vec2 { x: 0.0, y: 0.0 }),
+                FollowWithOffset {
+                    relative_pos: Position(Vec2 {
+                        x: 0.0,
+                        y: -crafter_height * 0.3,
+                    }),
+                    entity: crafter_entity,
+                },
+                LevelEntity,
+            ));
+            crafter_entity
+        }
+        _ => panic!("Wrong machine type given"),
+    }
+}
+
+// centers of the two intakes, relative to the machine
+fn two_to_one_crafter_intakes(crafter: &Machine) -> [Vec2; 2] {
+    let crafter_width = crafter.get_sprite_view().get_scale().x;
+    [
+        Vec2::new(-0.2 * crafter_width, 0.0),
+        Vec2::new(0.2 * crafter_width, 0.0),
+    ]
+}
+
+pub fn two_to_one_crafter(
+    mut commands: Commands,
+    mut items: Query<(Entity, &Item, &Position, &mut Velocity), With<Item>>,
+    mut rng: ResMut<SimulationRng>,
+    time: Res<Time>,
+    mut crafter: Query<
+        (
+            &Machine,
+            &Position,
+            &Rotation,
+            &mut IngredientBuffer,
+            &mut ProcessingQueue,
+        ),
+        Without<Item>,
+    >,
+) {
+    for (machine, machine_pos, rotation, mut buffer, mut queue) in crafter.iter_mut() {
+        let Machine::TwoToOneCrafter { inputs, output, .. } = *machine else {
+            continue;
+        };
+        let intake_radius = 0.15 * machine.get_sprite_view().get_scale().x;
+        let intakes = two_to_one_crafter_intakes(machine);
+        for (item_entity, item, item_pos, mut item_vel) in items.iter_mut() {
+            if !intakes.iter().any(|intake| {
+                (machine_pos.0 + rotation.rot2() * *intake).distance(item_pos.0) < intake_radius
+            }) {
+                continue;
+            }
+            // item inside an intake, anything but an ingredient is turned away
+            if !inputs.contains(item) {
+                bounce_off(rotation, &mut item_vel);
+                continue;
+            }
+            let free_slot = (0..2).find(|slot| inputs[*slot] == *item && !buffer.0[*slot]);
+            if let Some(slot) = free_slot {
+                buffer.0[slot] = true;
+                commands.entity(item_entity).despawn();
+            }
+            // otherwise the item waits in the intake until the buffer has room again
+        }
+
+        // a full queue keeps the buffer full, so further ingredients wait on the floor
+        if buffer.0 == [true, true] && !queue.is_full(machine) {
+            buffer.0 = [false, false];
+            queue.products.push_back(output);
+        }
+
+        if let Some(product) = queue.process(time.delta_secs(), machine.get_processing_time()) {
+            // spawn product
+            let random_velocity_x = rng.random_range(-50.0..50.0);
+            commands.spawn((
+                SpriteView::Item {
+                    item: product,
+                    ui_element: false,
+                },
+                product,
+                Position(
+                    machine_pos.0
+                        + rotation.rot2()
+                            * Vec2 {
+                                x: 0.0,
+                                // below the floor
+                                y: -machine.get_sprite_view().get_scale().y * 0.8 / 2.0,
+                            },
+                ),
+                CirclePhysics { radius: 14.0 },
+                Velocity(
+                    rotation.rot2()
+                        * Vec2 {
+                            x: random_velocity_x,
+                            y: 0.,
+                        },
+                ),
+                LevelEntity,
+            ));
+        }
+    }
+}
+
+pub fn spawn_duplicator(mut commands: Commands, position: Position, duplicator: Machine) -> Entity {
     match duplicator {
         Machine::Duplicator { cost } => {
             let duplicator_width = duplicator.get_sprite_view().get_scale().x;
@@ -219,72 +657,89 @@ pub fn spawn_duplicator(mut commands: Commands, position: Position, duplicator:
                     offset_y: 0.0,
                 },
             ];
-            commands.spawn((
-                duplicator.get_sprite_view(),
-                position,
-                ColliderCollection(physics_colliders),
-                duplicator,
-                Clickable,
-                DragAble,
-            ));
+            let duplicator_entity = commands
+                .spawn((
+                    duplicator.get_sprite_view(),
+                    position,
+                    Rotation::default(),
+                    ColliderCollection(physics_colliders),
+                    duplicator,
+                    ProcessingQueue::default(),
+                    Clickable,
+                    DragAble,
+                    LevelEntity,
+                ))
+                .id();
+            spawn_progress_bar(commands.reborrow(), duplicator_entity);
+            duplicator_entity
         }
-        _ => info!("Wrong machine type given"),
+        _ => panic!("Wrong machine type given"),
     }
 }
 
 pub fn duplicator(
     mut commands: Commands,
-    items: Query<(Entity, &Item, &Position), With<Item>>,
-    duplicator: Query<(&Machine, &Position), Without<Item>>,
+    mut items: Query<(Entity, &Item, &Position, &mut Velocity), With<Item>>,
+    mut rng: ResMut<SimulationRng>,
+    time: Res<Time>,
+    mut duplicator: Query<(&Machine, &Position, &Rotation, &mut ProcessingQueue), Without<Item>>,
 ) {
-    for (machine, machine_pos) in duplicator.iter() {
+    for (machine, machine_pos, rotation, mut queue) in duplicator.iter_mut() {
         match machine {
             Machine::Duplicator { cost } => {
-                for (item_entity, item, item_pos) in items.iter() {
-                    if (machine_pos.0.distance(item_pos.0)
-                        < 0.5625 * machine.get_sprite_view().get_scale().x / 2.0)
+                for (item_entity, item, item_pos, mut item_vel) in items.iter_mut() {
+                    // the pickup radius reaches below the walls, where the copies leave the machine.
+                    // items down there are on their way out and must not be duplicated again.
+                    let local_item_pos = rotation.rot2().inverse() * (item_pos.0 - machine_pos.0);
+                    let below_walls =
+                        local_item_pos.y < -machine.get_sprite_view().get_scale().y * 0.7 / 2.0;
+                    if machine_pos.0.distance(item_pos.0)
+                        < 0.5625 * machine.get_sprite_view().get_scale().x / 2.0
+                        && !below_walls
                     {
                         // item inside machine
-                        // spawn product
-                        let mut rng = rand::rng();
-                        let random_velocity_x = rng.random_range(-50.0..50.0);
-                        commands.spawn((
-                            SpriteView::Item {
-                                item: item.clone(),
-                                ui_element: false,
-                            },
-                            item.clone(),
-                            Position(Vec2 {
-                                x: machine_pos.0.x - machine.get_sprite_view().get_scale().x * 0.25,
-                                y: machine_pos.0.y
-                                    - machine.get_sprite_view().get_scale().y * 0.6 / 2.0,
-                            }),
-                            CirclePhysics { radius: 14.0 },
-                            Velocity(Vec2 {
-                                x: random_velocity_x,
-                                y: 0.,
-                            }),
-                        ));
-                        let random_velocity_x_2 = rng.random_range(-50.0..50.0);
+                        if queue.is_full(machine) {
+                            bounce_off(rotation, &mut item_vel);
+                            continue;
+                        }
+                        queue.products.push_back(*item);
+                        commands.entity(item_entity).despawn();
+                    }
+                }
 
+                if let Some(product) =
+                    queue.process(time.delta_secs(), machine.get_processing_time())
+                {
+                    // spawn a copy below each wall
+                    for side in [-1.0, 1.0] {
+                        let random_velocity_x = rng.random_range(-50.0..50.0);
                         commands.spawn((
                             SpriteView::Item {
-                                item: item.clone(),
+                                item: product,
                                 ui_element: false,
                             },
-                            item.clone(),
-                            Position(Vec2 {
-                                x: machine_pos.0.x + machine.get_sprite_view().get_scale().x * 0.25,
-                                y: machine_pos.0.y
-                                    - machine.get_sprite_view().get_scale().y * 0.6 / 2.0,
-                            }),
+                            product,
+                            Position(
+                                machine_pos.0
+                                    + rotation.rot2()
+                                        * Vec2 {
+                                            x: side
+                                                * machine.get_sprite_view().get_scale().x
+                                                * 0.25,
+                                            // just below the walls, so piled up copies can't be pushed back into the machine
+                                            y: -machine.get_sprite_view().get_scale().y * 0.8 / 2.0,
+                                        },
+                            ),
                             CirclePhysics { radius: 14.0 },
-                            Velocity(Vec2 {
-                                x: random_velocity_x_2,
-                                y: 0.,
-                            }),
+                            Velocity(
+                                rotation.rot2()
+                                    * Vec2 {
+                                        x: random_velocity_x,
+                                        y: 0.,
+                                    },
+                            ),
+                            LevelEntity,
                         ));
-                        commands.entity(item_entity).despawn();
                     }
                 }
             }
@@ -292,3 +747,152 @@ pub fn duplicator(
         }
     }
 }
+
+// same shell as the one to one crafter in other colors. the arrow shows the side matching items leave on
+pub fn spawn_sorter(mut commands: Commands, position: Position, sorter: Machine) -> Entity {
+    match sorter {
+        Machine::Sorter { item, .. } => {
+            let sorter_width = sorter.get_sprite_view().get_scale().x;
+            let sorter_height = sorter.get_sprite_view().get_scale().y;
+            let physics_colliders = vec![
+                RectanglePhysics {
+                    width: 0.0625 * sorter_width,
+                    height: 1.0 * sorter_height,
+                    offset_x: -0.5625 * sorter_width / 2.0,
+                    offset_y: 0.0,
+                },
+                RectanglePhysics {
+                    width: 0.0625 * sorter_width,
+                    height: 1.0 * sorter_height,
+                    offset_x: 0.5625 * sorter_width / 2.0,
+                    offset_y: 0.0,
+                },
+            ];
+            let sorter_entity = commands
+                .spawn((
+                    sorter.get_sprite_view(),
+                    position,
+                    Rotation::default(),
+                    ColliderCollection(physics_colliders),
+                    sorter,
+                    Clickable,
+                    DragAble,
+                    LevelEntity,
+                ))
+                .id();
+
+            // add item icon and arrow
+            commands.spawn((
+                SpriteView::Item {
+                    item,
+                    ui_element: true,
+                },
+                Position(Vec2 { x: 0.0, y: 0.0 }),
+                FollowWithOffset {
+                    relative_pos: Position(Vec2 {
+                        x: 0.0,
+                        y: sorter_height * 0.3,
+                    }),
+                    entity: sorter_entity,
+                },
+                LevelEntity,
+            ));
+            commands.spawn((
+                SpriteView::ArrowLeft,
+                Position(Vec2 { x: 0.0, y: 0.0 }),
+                FollowWithOffset {
+                    relative_pos: Position(Vec2 {
+                        x: 0.0,
+                        y: -sorter_height * 0.3,
+                    }),
+                    entity: sorter_entity,
+                },
+                LevelEntity,
+            ));
+            sorter_entity
+        }
+        _ => panic!("Wrong machine type given"),
+    }
+}
+
+const SORTER_EJECT_SPEED: f32 = 150.0;
+
+// moves items that enter the sorter out below it: matching items to the left, everything else to the right.
+// left, right and below as seen from the turned sorter
+pub fn sorter(
+    mut items: Query<(
+        &Item,
+        &mut Position,
+        &mut Velocity,
+        Option<&mut PreviousPosition>,
+    )>,
+    sorter: Query<(&Machine, &Position, &Rotation), Without<Item>>,
+) {
+    for (machine, machine_pos, rotation) in sorter.iter() {
+        let Machine::Sorter { item: filter, .. } = *machine else {
+            continue;
+        };
+        let scale = machine.get_sprite_view().get_scale();
+        for (item, mut item_pos, mut item_vel, previous_pos) in items.iter_mut() {
+            if machine_pos.0.distance(item_pos.0) >= 0.5625 * scale.x / 2.0 {
+                continue;
+            }
+            // item inside machine
+            let side = if *item == filter { -1.0 } else { 1.0 };
+            // below the walls, so the item can't fall back in
+            item_pos.0 =
+                machine_pos.0 + rotation.rot2() * Vec2::new(side * 0.3 * scale.x, -0.6 * scale.y);
+            item_vel.0 = rotation.rot2() * Vec2::new(side * SORTER_EJECT_SPEED, 0.0);
+            // don't interpolate the jump
+            if let Some(mut previous_pos) = previous_pos {
+                previous_pos.0 = item_pos.0;
+            }
+        }
+    }
+}
+
+// a flat belt, only the surface velocity moves items along it
+pub fn conveyor_bundle(
+    position: Position,
+    direction: BeltDirection,
+    speed: f32,
+) -> (
+    SpriteView,
+    Position,
+    Rotation,
+    ColliderCollection,
+    SurfaceVelocity,
+    LevelEntity,
+) {
+    let view = SpriteView::Conveyor { direction };
+    let collider = RectanglePhysics {
+        width: view.get_scale().x,
+        height: 0.8 * view.get_scale().y,
+        offset_x: 0.0,
+        offset_y: 0.0,
+    };
+    (
+        view,
+        position,
+        Rotation::default(),
+        ColliderCollection(vec![collider]),
+        SurfaceVelocity(direction.get_sign() * speed),
+        LevelEntity,
+    )
+}
+
+pub fn spawn_conveyor(mut commands: Commands, position: Position, conveyor: Machine) -> Entity {
+    match conveyor {
+        Machine::Conveyor {
+            direction, speed, ..
+        } => commands
+            .spawn((
+                conveyor_bundle(position, direction, speed),
+                conveyor,
+                Clickable,
+                DragAble,
+            ))
+            .id(),
+        _ => panic!("Wrong machine type given"),
+    }
+}
@@ -0,0 +1,143 @@
+use crate::game::*;
+
+// root of the main menu
+#[derive(Component)]
+pub struct MainMenu;
+
+#[derive(Component)]
+pub struct LevelButton(pub u32);
+
+const BUTTON_COLOR: Color = Color::srgb(0.25, 0.25, 0.3);
+const BUTTON_HOVER_COLOR: Color = Color::srgb(0.4, 0.4, 0.45);
+const LOCKED_BUTTON_COLOR: Color = Color::srgb(0.12, 0.12, 0.14);
+
+pub fn level_buttons(
+    mut buttons: Query<(&Interaction, &LevelButton, &mut BackgroundColor), Changed<Interaction>>,
+    progress: Res<Progress>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut next_app_state: ResMut<NextState<AppState>>,
+) {
+    for (interaction, button, mut color) in buttons.iter_mut() {
+        if !progress.is_unlocked(button.0) {
+            continue;
+        }
+        match interaction {
+            Interaction::Pressed => {
+                // entering the level state loads the level
+                current_level.0 = button.0;
+                next_app_state.set(AppState::InLevel);
+            }
+            Interaction::Hovered => *color = BackgroundColor(BUTTON_HOVER_COLOR),
+            Interaction::None => *color = BackgroundColor(BUTTON_COLOR),
+        }
+    }
+}
+
+// title and a grid with a button per level in the registry, shown in `AppState::Menu`
+pub fn spawn_main_menu(
+    mut commands: Commands,
+    level_registry: Res<LevelRegistry>,
+    progress: Res<Progress>,
+    asset_server: Res<AssetServer>,
+) {
+    let font = asset_server.load("Fonts/CyberpunkCraftpixPixel.otf");
+    let mut levels: Vec<u32> = level_registry.0.keys().copied().collect();
+    levels.sort();
+
+    commands
+        .spawn((
+            MainMenu,
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(30.0),
+                position_type: PositionType::Absolute,
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.05, 0.05, 0.07, 0.95)),
+            GlobalZIndex(100),
+            StateScoped(AppState::Menu),
+        ))
+        .with_children(|menu| {
+            menu.spawn((
+                Text::new("Assembly Anarchy"),
+                TextFont {
+                    font: font.clone(),
+                    font_size: 100.,
+                    ..default()
+                },
+                TextColor(Color::srgb(1.0, 215.0 / 255.0, 0.0)),
+            ));
+            menu.spawn((
+                Text::new("select a level"),
+                TextFont {
+                    font: font.clone(),
+                    font_size: 30.,
+                    ..default()
+                },
+                TextColor(Color::srgb(211.0 / 255.0, 211.0 / 255.0, 211.0 / 255.0)),
+            ));
+            menu.spawn(Node {
+                display: Display::Grid,
+                grid_template_columns: RepeatedGridTrack::px(5, 200.0),
+                row_gap: Val::Px(20.0),
+                column_gap: Val::Px(20.0),
+                ..default()
+            })
+            .with_children(|grid| {
+                for level in levels {
+                    let unlocked = progress.is_unlocked(level);
+                    let status = if !unlocked {
+                        "locked".to_string()
+                    } else if let Some(best) = progress.best_times.get(&level) {
+                        format!("best {:.1}s", best)
+                    } else {
+                        "not solved yet".to_string()
+                    };
+                    grid.spawn((
+                        Button,
+                        LevelButton(level),
+                        Node {
+                            height: Val::Px(100.0),
+                            flex_direction: FlexDirection::Column,
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        BackgroundColor(if unlocked {
+                            BUTTON_COLOR
+                        } else {
+                            LOCKED_BUTTON_COLOR
+                        }),
+                    ))
+                    .with_children(|button| {
+                        button.spawn((
+                            Text::new(format!("Level {}", level)),
+                            TextFont {
+                                font: font.clone(),
+                                font_size: 30.,
+                                ..default()
+                            },
+                            TextColor(if unlocked {
+                                Color::srgb(1.0, 215.0 / 255.0, 0.0)
+                            } else {
+                                Color::srgb(0.4, 0.4, 0.4)
+                            }),
+                        ));
+                        button.spawn((
+                            Text::new(status),
+                            TextFont {
+                                font: font.clone(),
+                                font_size: 15.,
+                                ..default()
+                            },
+                            TextColor(Color::srgb(211.0 / 255.0, 211.0 / 255.0, 211.0 / 255.0)),
+                        ));
+                    });
+                }
+            });
+        });
+}
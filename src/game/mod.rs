@@ -9,6 +9,9 @@ pub use simple_physics::*;
 pub mod levels;
 pub use levels::*;
 
+pub mod level_definition;
+pub use level_definition::*;
+
 pub mod input;
 pub use input::*;
 
@@ -21,8 +24,41 @@ pub use pipes::*;
 pub mod draw;
 pub use draw::*;
 
+pub mod history;
+pub use history::*;
+
+pub mod shapes;
+pub use shapes::*;
+
+pub mod placement;
+pub use placement::*;
+
+pub mod shop;
+pub use shop::*;
+
 pub mod ui;
 pub use ui::*;
 
 pub mod machines;
 pub use machines::*;
+
+pub mod simulation;
+pub use simulation::*;
+
+pub mod time_scale;
+pub use time_scale::*;
+
+pub mod run_phase;
+pub use run_phase::*;
+
+pub mod money;
+pub use money::*;
+
+pub mod income;
+pub use income::*;
+
+pub mod progress;
+pub use progress::*;
+
+pub mod menu;
+pub use menu::*;
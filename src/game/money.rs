@@ -0,0 +1,85 @@
+use crate::game::*;
+use bevy::ecs::event::EventCursor;
+use bevy::ecs::system::SystemParam;
+
+// Every change to `CurrentMoney` during a level, applied by `apply_money_changes`.
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct MoneyChanged {
+    pub amount: i32, // negative for expenses
+    pub reason: MoneyReason,
+    pub source_entity: Option<Entity>, // the pipe or machine, if there is one
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoneyReason {
+    ItemSpawned,   // paid to an input pipe
+    ItemDelivered, // reward from an output pipe
+    WrongItem,     // penalty from an output pipe
+    MachineBought,
+    MachineSold,
+    RunReset, // the money of a run given back, see `reset_run`
+}
+
+impl MoneyReason {
+    // money made or spent by a running layout, not by building it
+    pub fn is_production(&self) -> bool {
+        matches!(
+            self,
+            MoneyReason::ItemSpawned | MoneyReason::ItemDelivered | MoneyReason::WrongItem
+        )
+    }
+}
+
+// All money changes of the current level in order, cleared when a level is loaded.
+#[derive(Resource, Default, Debug)]
+pub struct MoneyLedger(pub Vec<MoneyChanged>);
+
+// how far `apply_money_changes` got, the changes after it are still pending
+#[derive(Resource, Default)]
+pub struct AppliedMoneyChanges(EventCursor<MoneyChanged>);
+
+// Money to buy with, `CurrentMoney` plus the changes not applied yet.
+// Sends money changes too, as an `EventWriter` can't be used next to it.
+#[derive(SystemParam)]
+pub struct Wallet<'w> {
+    money: Res<'w, CurrentMoney>,
+    changes: ResMut<'w, Events<MoneyChanged>>,
+    applied: Res<'w, AppliedMoneyChanges>,
+}
+
+impl Wallet<'_> {
+    pub fn balance(&self) -> i32 {
+        let mut pending = self.applied.0.clone();
+        self.money.0
+            + pending
+                .read(&self.changes)
+                .map(|change| change.amount)
+                .sum::<i32>()
+    }
+
+    pub fn send(&mut self, change: MoneyChanged) {
+        self.changes.send(change);
+    }
+}
+
+// the only system that changes `CurrentMoney` while a level is played
+pub fn apply_money_changes(
+    changes: Res<Events<MoneyChanged>>,
+    mut applied: ResMut<AppliedMoneyChanges>,
+    mut money: ResMut<CurrentMoney>,
+    mut ledger: ResMut<MoneyLedger>,
+) {
+    for change in applied.0.read(&changes) {
+        money.0 += change.amount;
+        ledger.0.push(*change);
+    }
+}
+
+// changes still pending from the previous level are dropped as well
+pub fn clear_money_ledger(
+    mut ledger: ResMut<MoneyLedger>,
+    mut changes: ResMut<Events<MoneyChanged>>,
+) {
+    ledger.0.clear();
+    changes.clear();
+}
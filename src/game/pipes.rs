@@ -2,6 +2,7 @@ use crate::game::*;
 use bevy::color::palettes::basic::*;
 
 use rand::Rng;
+use serde::Deserialize;
 
 // left most pipe (POS1) is blocked by UI element
 pub const INPUT_PIPE_POS2: Vec2 = Vec2::new(-300., 400.);
@@ -28,6 +29,47 @@ pub struct InputPipe {
 pub struct OutputPipe {
     pub item: Item,
     pub reward: i32, // money per item returned
+    pub wrong_items: WrongItems,
+    pub quality: f32, // 0..=1, the share of the reward paid out, lowered by `WrongItems::Contaminate`
+}
+
+// what an output pipe does with items it doesn't want
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+pub enum WrongItems {
+    #[default]
+    Ignore, // they stay in the pipe
+    Reject,           // bounced back out of the pipe
+    Penalty(i32),     // taken, costs this much money each
+    Contaminate(f32), // taken, lowers the pipe's quality by this much each
+}
+
+// quality a contaminated pipe wins back with every right item
+pub const QUALITY_RECOVERY: f32 = 0.05;
+
+// rejected items are thrown up and to the side, high enough to clear the pipe walls
+const REJECT_VELOCITY: Vec2 = Vec2::new(150.0, 600.0);
+
+// shows the quality of a contaminated output pipe
+#[derive(Component)]
+pub struct PipeQualityText(pub Entity);
+
+impl OutputPipe {
+    pub fn quality_reward(&self) -> i32 {
+        (self.reward as f32 * self.quality).round() as i32
+    }
+}
+
+// one sound per way an item can end up in an output pipe
+const DELIVERED_SOUND: &str = "Music/coin.ogg";
+const REJECTED_SOUND: &str = "Music/reject.ogg";
+const PENALTY_SOUND: &str = "Music/penalty.ogg";
+const CONTAMINATED_SOUND: &str = "Music/contaminate.ogg";
+
+fn play_sound(mut commands: Commands, asset_server: &AssetServer, path: &'static str) {
+    commands.spawn((
+        AudioPlayer(asset_server.load::<AudioSource>(path)),
+        PlaybackSettings::DESPAWN,
+    ));
 }
 
 pub fn on_add_input_pipe(
@@ -48,6 +90,7 @@ pub fn on_add_input_pipe(
                 x: pos.0.x,
                 y: pos.0.y + 15.,
             }),
+            LevelEntity,
         ));
 
         // add reward text
@@ -66,7 +109,7 @@ pub fn on_add_input_pipe(
                 left: Val::Px(pos.0.x + 800. + 10.),
                 ..default()
             },
-            Position(Vec2 { x: 0., y: 0. }), // de-spawn marker
+            LevelEntity,
         ));
     }
 }
@@ -101,6 +144,7 @@ pub fn on_add_output_pipe(
                 height: 1.0 * half_diameter,
                 ..Default::default()
             }]),
+            LevelEntity,
         ));
         //left side
         commands.spawn((
@@ -119,6 +163,7 @@ pub fn on_add_output_pipe(
                 height: 1.0 * half_diameter,
                 ..Default::default()
             }]),
+            LevelEntity,
         ));
 
         // add the item icon
@@ -131,6 +176,7 @@ pub fn on_add_output_pipe(
                 x: pos.0.x,
                 y: pos.0.y - 50.0,
             }),
+            LevelEntity,
         ));
 
         // add reward text
@@ -149,40 +195,122 @@ pub fn on_add_output_pipe(
                 left: Val::Px(pos.0.x + 800. + 10.),
                 ..default()
             },
-            Position(Vec2 { x: 0., y: 0. }), // de-spawn marker
+            LevelEntity,
         ));
+
+        if let WrongItems::Contaminate(_) = pipe.wrong_items {
+            // in the world, just above the reward
+            commands.spawn((
+                PipeQualityText(entity),
+                Text2d::new(""),
+                TextFont {
+                    font: asset_server.load("Fonts/CyberpunkCraftpixPixel.otf"),
+                    font_size: 16.,
+                    ..default()
+                },
+                TextColor(Color::srgb(211.0 / 255.0, 211.0 / 255.0, 211.0 / 255.0)),
+                Transform::from_xyz(pos.0.x, pos.0.y - half_diameter + 55.0, 200.0),
+                LevelEntity,
+            ));
+        }
+    }
+}
+
+pub fn update_pipe_quality_texts(
+    pipes: Query<&OutputPipe>,
+    mut texts: Query<(&PipeQualityText, &mut Text2d)>,
+) {
+    for (quality_text, mut text) in texts.iter_mut() {
+        if let Ok(pipe) = pipes.get(quality_text.0) {
+            text.0 = format!("quality {:.0}%", pipe.quality * 100.0);
+        }
     }
 }
 
 pub fn output_pipe_consume_item(
     mut commands: Commands,
-    items: Query<(Entity, &Item, &Position)>,
-    pipes: Query<(&OutputPipe, &Position, &SpriteView)>,
-    mut money: ResMut<CurrentMoney>,
+    mut items: Query<(Entity, &Item, &Position, &mut Velocity)>,
+    mut pipes: Query<(Entity, &mut OutputPipe, &Position, &SpriteView)>,
+    mut stats: ResMut<SimulationStats>,
+    mut money_changes: EventWriter<MoneyChanged>,
     asset_server: Res<AssetServer>,
 ) {
-    for (pipe, pipe_pos, pipe_view) in pipes.iter() {
+    for (pipe_entity, mut pipe, pipe_pos, pipe_view) in pipes.iter_mut() {
         let half_diameter = pipe_view.get_scale().y / 2.0;
         let pipe_collection_point = Vec2 {
             x: pipe_pos.0.x,
             y: pipe_pos.0.y - half_diameter,
         };
         let collection_diameter = 0.5 * half_diameter;
-        for (item_entity, item, item_pos) in items.iter() {
-            if (item_pos.0.distance(pipe_collection_point) < collection_diameter) {
-                if (*item == pipe.item) {
-                    money.0 += pipe.reward;
+        for (item_entity, item, item_pos, mut velocity) in items.iter_mut() {
+            if item_pos.0.distance(pipe_collection_point) >= collection_diameter {
+                continue;
+            }
+            let popup_position = Vec2::new(pipe_pos.0.x, pipe_pos.0.y + half_diameter);
+            if *item == pipe.item {
+                let reward = pipe.quality_reward();
+                money_changes.send(MoneyChanged {
+                    amount: reward,
+                    reason: MoneyReason::ItemDelivered,
+                    source_entity: Some(pipe_entity),
+                });
+                stats.items_consumed += 1;
+                pipe.quality = (pipe.quality + QUALITY_RECOVERY).min(1.0);
+                commands.entity(item_entity).despawn();
+                play_sound(commands.reborrow(), &asset_server, DELIVERED_SOUND);
+                continue;
+            }
+            match pipe.wrong_items {
+                WrongItems::Ignore => {}
+                // only falling items, one already thrown back up is on its way out
+                WrongItems::Reject if velocity.0.y > 0.0 => {}
+                WrongItems::Reject => {
+                    // thrown out on the side it came in
+                    let side = if item_pos.0.x < pipe_pos.0.x {
+                        -1.0
+                    } else {
+                        1.0
+                    };
+                    velocity.0 = Vec2::new(side * REJECT_VELOCITY.x, REJECT_VELOCITY.y);
+                    spawn_floating_text(
+                        commands.reborrow(),
+                        &asset_server,
+                        "rejected".to_string(),
+                        Color::srgb(211.0 / 255.0, 211.0 / 255.0, 211.0 / 255.0),
+                        popup_position,
+                    );
+                    play_sound(commands.reborrow(), &asset_server, REJECTED_SOUND);
+                }
+                WrongItems::Penalty(penalty) => {
+                    money_changes.send(MoneyChanged {
+                        amount: -penalty,
+                        reason: MoneyReason::WrongItem,
+                        source_entity: Some(pipe_entity),
+                    });
+                    stats.items_consumed += 1;
                     commands.entity(item_entity).despawn();
-                    let music_handle = asset_server.load::<AudioSource>("Music/coin.ogg");
-
-                    // Spawn an entity to play the music
-                    commands.spawn((
-                        AudioPlayer(music_handle),
-                        PlaybackSettings {
-                            mode: bevy::audio::PlaybackMode::Despawn,
-                            ..default()
-                        },
-                    ));
+                    // above the money popup
+                    spawn_floating_text(
+                        commands.reborrow(),
+                        &asset_server,
+                        "wrong item".to_string(),
+                        Color::srgb(1.0, 130.0 / 255.0, 130.0 / 255.0),
+                        popup_position + Vec2::new(0., 30.),
+                    );
+                    play_sound(commands.reborrow(), &asset_server, PENALTY_SOUND);
+                }
+                WrongItems::Contaminate(loss) => {
+                    pipe.quality = (pipe.quality - loss).max(0.0);
+                    stats.items_consumed += 1;
+                    commands.entity(item_entity).despawn();
+                    spawn_floating_text(
+                        commands.reborrow(),
+                        &asset_server,
+                        format!("contaminated {:.0}%", pipe.quality * 100.0),
+                        Color::srgb(150.0 / 255.0, 200.0 / 255.0, 90.0 / 255.0),
+                        popup_position,
+                    );
+                    play_sound(commands.reborrow(), &asset_server, CONTAMINATED_SOUND);
                 }
             }
         }
@@ -191,8 +319,13 @@ pub fn output_pipe_consume_item(
 
 pub fn toggle_input_pipe(
     clicked_resource: Res<JustClicked>,
+    phase: Res<RunPhase>,
     mut pipes: Query<(Entity, &mut InputPipe)>,
 ) {
+    // opened by the run button instead
+    if phase.pipes_locked() {
+        return;
+    }
     for (pipe_entity, mut pipe) in pipes.iter_mut() {
         if let Some(clicked_entity) = clicked_resource.0 {
             if (clicked_entity == pipe_entity) {
@@ -206,7 +339,9 @@ pub fn input_pipe_spawn_item(
     mut commands: Commands,
     mut pipes: Query<(Entity, &mut InputPipe, &Position)>,
     time: Res<Time>,
-    mut money: ResMut<CurrentMoney>,
+    mut stats: ResMut<SimulationStats>,
+    mut rng: ResMut<SimulationRng>,
+    mut money_changes: EventWriter<MoneyChanged>,
 ) {
     for (pipe_entity, mut input_pipe, pipe_position) in pipes.iter_mut() {
         if (!input_pipe.enabled) {
@@ -224,7 +359,6 @@ pub fn input_pipe_spawn_item(
 
         // Check if enough time has elapsed to spawn an item.
         while input_pipe.time_elapsed >= spawn_interval {
-            let mut rng = rand::rng();
             let random_velocity_x = rng.random_range(-50.0..50.0);
             commands.spawn((
                 SpriteView::Item {
@@ -241,10 +375,16 @@ pub fn input_pipe_spawn_item(
                     x: random_velocity_x,
                     y: 0.,
                 }),
+                LevelEntity,
             ));
 
             // pay the price for the item
-            money.0 -= input_pipe.cost;
+            money_changes.send(MoneyChanged {
+                amount: -input_pipe.cost,
+                reason: MoneyReason::ItemSpawned,
+                source_entity: Some(pipe_entity),
+            });
+            stats.items_spawned += 1;
 
             // Subtract the spawn interval from time_elapsed. This is crucial for accuracy.
             // Don't just reset to 0, in case `time_elapsed` accumulated much more than `spawn_interval`.
@@ -0,0 +1,205 @@
+use crate::game::*;
+
+// A bought machine waiting to be placed, money is only taken once it is placed.
+#[derive(Resource, Default)]
+pub struct MachinePlacement(pub Option<ShopEntry>);
+
+// see-through machine following the cursor while placing
+#[derive(Component)]
+pub struct PlacementGhost;
+
+// static colliders a machine can't be placed on
+pub type PlacementObstacles<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Position,
+        Option<&'static ColliderCollection>,
+        Option<&'static StrokePhysics>,
+        Option<&'static Rotation>,
+    ),
+    (Without<Velocity>, Without<PlacementGhost>),
+>;
+
+// the area the machine's sprite covers
+pub fn machine_footprint(machine: &Machine, position: Vec2) -> Rect {
+    Rect::from_center_size(position, machine.get_sprite_view().get_scale())
+}
+
+// whether the segment from `start` to `end` passes through `rect`
+fn segment_crosses_rect(start: Vec2, end: Vec2, rect: Rect) -> bool {
+    let direction = end - start;
+    let (mut enter, mut exit) = (0.0f32, 1.0f32);
+    for axis in 0..2 {
+        let (from, along, min, max) =
+            (start[axis], direction[axis], rect.min[axis], rect.max[axis]);
+        if along == 0.0 {
+            if from < min || from > max {
+                return false;
+            }
+            continue;
+        }
+        let (near, far) = ((min - from) / along, (max - from) / along);
+        enter = enter.max(near.min(far));
+        exit = exit.min(near.max(far));
+        if enter > exit {
+            return false;
+        }
+    }
+    true
+}
+
+fn distance_to_segment(point: Vec2, start: Vec2, end: Vec2) -> f32 {
+    let segment = end - start;
+    let along = (point - start).dot(segment) / segment.length_squared().max(f32::EPSILON);
+    point.distance(start + segment * along.clamp(0.0, 1.0))
+}
+
+// whether a capsule of a stroke reaches into `rect`
+fn capsule_overlaps_rect(start: Vec2, end: Vec2, radius: f32, rect: Rect) -> bool {
+    if segment_crosses_rect(start, end, rect) {
+        return true;
+    }
+    let corners = [
+        rect.min,
+        rect.max,
+        Vec2::new(rect.min.x, rect.max.y),
+        Vec2::new(rect.max.x, rect.min.y),
+    ];
+    let to_ends = [start, end].map(|point| point.distance(point.clamp(rect.min, rect.max)));
+    let to_corners = corners.map(|corner| distance_to_segment(corner, start, end));
+    to_ends
+        .into_iter()
+        .chain(to_corners)
+        .any(|distance| distance < radius)
+}
+
+// strokes are checked segment by segment, a diagonal stroke's bounding box would block far too much
+pub fn placement_blocked(
+    machine: &Machine,
+    position: Vec2,
+    obstacles: &PlacementObstacles,
+) -> bool {
+    let footprint = machine_footprint(machine, position);
+    obstacles.iter().any(|(pos, colliders, stroke, rotation)| {
+        if let Some(colliders) = colliders {
+            let rotation = rotation.map_or(Rot2::IDENTITY, Rotation::rot2);
+            let bounds = collider_collection_bounds(pos.0, colliders, rotation);
+            if !bounds.intersect(footprint).is_empty() {
+                return true;
+            }
+        }
+        stroke.is_some_and(|stroke| {
+            stroke.segments().any(|(start, end)| {
+                capsule_overlaps_rect(pos.0 + start, pos.0 + end, stroke.radius, footprint)
+            })
+        })
+    })
+}
+
+pub fn start_placement(
+    mut commands: Commands,
+    placement: &mut MachinePlacement,
+    ghosts: &Query<Entity, With<PlacementGhost>>,
+    entry: ShopEntry,
+    cursor: Position,
+) {
+    // buying another machine replaces the one being placed
+    for ghost in ghosts.iter() {
+        commands.entity(ghost).despawn();
+    }
+    placement.0 = Some(entry);
+    commands.spawn((
+        entry.machine.get_sprite_view(),
+        cursor,
+        PlacementGhost,
+        LevelEntity,
+    ));
+}
+
+fn stop_placement(
+    mut commands: Commands,
+    placement: &mut MachinePlacement,
+    ghosts: &Query<Entity, With<PlacementGhost>>,
+) {
+    placement.0 = None;
+    for ghost in ghosts.iter() {
+        commands.entity(ghost).despawn();
+    }
+}
+
+// a click on a free spot places the machine, if it is still affordable and in stock
+#[allow(clippy::too_many_arguments)]
+pub fn place_machine(
+    mut commands: Commands,
+    buttons: Res<ButtonInput<MouseButton>>,
+    mouse_pos: Res<MouseWorldPosition>,
+    over_element: Res<OverClickableElement>,
+    mut placement: ResMut<MachinePlacement>,
+    mut wallet: Wallet,
+    mut stock: ResMut<ShopStock>,
+    phase: Res<RunPhase>,
+    obstacles: PlacementObstacles,
+    ghosts: Query<Entity, With<PlacementGhost>>,
+) {
+    let Some(entry) = placement.0 else {
+        return;
+    };
+    let Some(mouse_position) = mouse_pos.0 else {
+        return;
+    };
+    if !buttons.just_pressed(MouseButton::Left)
+        || over_element.0.is_some()
+        || !stock.available(entry.slot)
+        || !phase.shop_open()
+        || placement_blocked(&entry.machine, mouse_position.0, &obstacles)
+    {
+        return;
+    }
+    if let Some(machine) = try_buy_machine(
+        commands.reborrow(),
+        &mut wallet,
+        entry.machine,
+        mouse_position,
+    ) {
+        commands.entity(machine).insert(ShopSlot(entry.slot));
+        stock.take(entry.slot);
+        stop_placement(commands, &mut placement, &ghosts);
+    }
+}
+
+// the ghost turns red over other colliders
+pub fn update_placement_ghost(
+    placement: Res<MachinePlacement>,
+    mouse_pos: Res<MouseWorldPosition>,
+    obstacles: PlacementObstacles,
+    mut ghosts: Query<(&mut Position, Option<&mut Sprite>), With<PlacementGhost>>,
+) {
+    let (Some(entry), Some(mouse_position)) = (placement.0, mouse_pos.0) else {
+        return;
+    };
+    let blocked = placement_blocked(&entry.machine, mouse_position.0, &obstacles);
+    for (mut pos, sprite) in ghosts.iter_mut() {
+        pos.0 = mouse_position.0;
+        // the sprite is added by `on_add_view` a frame later
+        if let Some(mut sprite) = sprite {
+            sprite.color = if blocked {
+                Color::srgba(1.0, 0.3, 0.3, 0.6)
+            } else {
+                Color::srgba(1.0, 1.0, 1.0, 0.6)
+            };
+        }
+    }
+}
+
+// Escape cancels placing instead of pausing, so this runs after `pause_level`
+pub fn cancel_placement(
+    commands: Commands,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut placement: ResMut<MachinePlacement>,
+    ghosts: Query<Entity, With<PlacementGhost>>,
+) {
+    if placement.0.is_some() && keys.just_pressed(KeyCode::Escape) {
+        stop_placement(commands, &mut placement, &ghosts);
+    }
+}
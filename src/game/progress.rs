@@ -0,0 +1,113 @@
+use crate::game::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// File name of the save (native), or its key in localStorage (web).
+pub const SAVE_NAME: &str = "assembly_anarchy_save.ron";
+
+// Unlocked levels and best times, kept between launches.
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Progress {
+    pub unlocked: u32, // highest level that can be started from the menu
+    #[serde(default)]
+    pub best_times: BTreeMap<u32, f32>, // seconds it took to reach the money goal
+}
+
+impl Default for Progress {
+    fn default() -> Self {
+        // level 0 only explains the game, so level 1 is open from the start
+        Progress {
+            unlocked: 1,
+            best_times: BTreeMap::new(),
+        }
+    }
+}
+
+impl Progress {
+    pub fn is_unlocked(&self, level: u32) -> bool {
+        level <= self.unlocked
+    }
+
+    // unlocks the next level and keeps the time if it is a new best. returns false if nothing changed
+    pub fn record_win(&mut self, level: u32, seconds: f32) -> bool {
+        let mut changed = false;
+        if level + 1 > self.unlocked {
+            self.unlocked = level + 1;
+            changed = true;
+        }
+        if self
+            .best_times
+            .get(&level)
+            .is_none_or(|best| seconds < *best)
+        {
+            self.best_times.insert(level, seconds);
+            changed = true;
+        }
+        changed
+    }
+
+    // a missing or broken save starts from scratch
+    pub fn load() -> Progress {
+        let Some(source) = read_save() else {
+            return Progress::default();
+        };
+        match ron::from_str(&source) {
+            Ok(progress) => progress,
+            Err(error) => {
+                warn!("could not read save {}: {}", SAVE_NAME, error);
+                Progress::default()
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        let result = ron::to_string(self)
+            .map_err(|error| error.to_string())
+            .and_then(|source| write_save(&source));
+        if let Err(error) = result {
+            warn!("could not write save {}: {}", SAVE_NAME, error);
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read_save() -> Option<String> {
+    std::fs::read_to_string(SAVE_NAME).ok()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write_save(source: &str) -> Result<(), String> {
+    std::fs::write(SAVE_NAME, source).map_err(|error| error.to_string())
+}
+
+// The web build can't write to disk, the browser keeps the save instead.
+#[cfg(target_arch = "wasm32")]
+fn local_storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+fn read_save() -> Option<String> {
+    local_storage()?.get_item(SAVE_NAME).ok()?
+}
+
+#[cfg(target_arch = "wasm32")]
+fn write_save(source: &str) -> Result<(), String> {
+    local_storage()
+        .ok_or("localStorage is not available")?
+        .set_item(SAVE_NAME, source)
+        .map_err(|_| "localStorage refused the save".to_string())
+}
+
+// Runs on entering `LevelState::Won` in the windowed game only, so headless runs never touch the save.
+pub fn record_progress(
+    current_level: Res<CurrentLevel>,
+    stats: Res<SimulationStats>,
+    fixed_time: Res<Time<Fixed>>,
+    mut progress: ResMut<Progress>,
+) {
+    let seconds = stats.ticks as f32 * fixed_time.timestep().as_secs_f32();
+    if progress.record_win(current_level.0, seconds) {
+        progress.save();
+    }
+}
@@ -0,0 +1,168 @@
+use crate::game::*;
+
+const BUTTON_COLOR: Color = Color::srgb(0.25, 0.25, 0.3);
+const BUTTON_HOVER_COLOR: Color = Color::srgb(0.4, 0.4, 0.45);
+
+// Levels with `build_phase` start with the input pipes locked, so nothing costs money while building.
+// The run button opens the pipes, resetting the run clears the items and gives the money back.
+#[derive(Resource, Default, Debug, Clone, Copy, PartialEq)]
+pub enum RunPhase {
+    #[default]
+    Free, // the level has no build phase, pipes can be toggled at any time
+    Building,
+    Running {
+        starting_money: i32, // money when the run was started, restored by `reset_run`
+    },
+}
+
+impl RunPhase {
+    pub fn pipes_locked(&self) -> bool {
+        *self == RunPhase::Building
+    }
+
+    // machines are only bought and sold outside of a run, so resetting it can't make money
+    pub fn shop_open(&self) -> bool {
+        !matches!(self, RunPhase::Running { .. })
+    }
+}
+
+#[derive(Component)]
+pub struct RunButton;
+
+pub fn start_run(phase: &mut RunPhase, wallet: &Wallet, pipes: &mut Query<&mut InputPipe>) {
+    *phase = RunPhase::Running {
+        starting_money: wallet.balance(),
+    };
+    for mut pipe in pipes.iter_mut() {
+        pipe.enabled = true;
+    }
+}
+
+// back to the build phase, the layout stays as it is
+#[allow(clippy::too_many_arguments)]
+pub fn reset_run(
+    mut commands: Commands,
+    phase: &mut RunPhase,
+    wallet: &mut Wallet,
+    stats: &mut SimulationStats,
+    income: &mut IncomeMeter,
+    rng: &mut SimulationRng,
+    items: &Query<Entity, (With<Item>, With<Velocity>)>,
+    pipes: &mut Query<&mut InputPipe>,
+    output_pipes: &mut Query<&mut OutputPipe>,
+    machines: &mut Query<(&mut ProcessingQueue, Option<&mut IngredientBuffer>)>,
+) {
+    let RunPhase::Running { starting_money } = *phase else {
+        return;
+    };
+    *phase = RunPhase::Building;
+    wallet.send(MoneyChanged {
+        amount: starting_money - wallet.balance(),
+        reason: MoneyReason::RunReset,
+        source_entity: None,
+    });
+    *stats = SimulationStats::default();
+    income.clear();
+    rng.reset();
+    for item in items.iter() {
+        commands.entity(item).despawn();
+    }
+    for mut pipe in pipes.iter_mut() {
+        pipe.enabled = false;
+        pipe.time_elapsed = 0.;
+    }
+    for mut pipe in output_pipes.iter_mut() {
+        pipe.quality = 1.0;
+    }
+    for (mut queue, buffer) in machines.iter_mut() {
+        *queue = ProcessingQueue::default();
+        if let Some(mut buffer) = buffer {
+            *buffer = IngredientBuffer::default();
+        }
+    }
+}
+
+// "run" while building, "reset run" while running.
+// Runs after `apply_money_changes`, so a reset sees all the money the run made.
+#[allow(clippy::too_many_arguments)]
+pub fn run_button(
+    commands: Commands,
+    mut buttons: Query<(Ref<Interaction>, &mut BackgroundColor, &Children), With<RunButton>>,
+    mut texts: Query<&mut Text>,
+    mut phase: ResMut<RunPhase>,
+    mut wallet: Wallet,
+    mut stats: ResMut<SimulationStats>,
+    mut income: ResMut<IncomeMeter>,
+    mut rng: ResMut<SimulationRng>,
+    items: Query<Entity, (With<Item>, With<Velocity>)>,
+    mut pipes: Query<&mut InputPipe>,
+    mut output_pipes: Query<&mut OutputPipe>,
+    mut machines: Query<(&mut ProcessingQueue, Option<&mut IngredientBuffer>)>,
+) {
+    let Ok((interaction, mut color, children)) = buttons.get_single_mut() else {
+        return;
+    };
+    *color = BackgroundColor(if *interaction == Interaction::None {
+        BUTTON_COLOR
+    } else {
+        BUTTON_HOVER_COLOR
+    });
+    if *interaction == Interaction::Pressed && interaction.is_changed() {
+        match *phase {
+            RunPhase::Building => start_run(&mut phase, &wallet, &mut pipes),
+            RunPhase::Running { .. } => reset_run(
+                commands,
+                &mut phase,
+                &mut wallet,
+                &mut stats,
+                &mut income,
+                &mut rng,
+                &items,
+                &mut pipes,
+                &mut output_pipes,
+                &mut machines,
+            ),
+            RunPhase::Free => {}
+        }
+    }
+    if phase.is_changed() {
+        for child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(*child) {
+                text.0 = match *phase {
+                    RunPhase::Running { .. } => "reset run".to_string(),
+                    _ => "run".to_string(),
+                };
+            }
+        }
+    }
+}
+
+pub fn spawn_run_button(mut commands: Commands, asset_server: &Res<AssetServer>) {
+    commands
+        .spawn((
+            Button,
+            RunButton,
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(10.0),
+                left: Val::Px(920.0),
+                width: Val::Px(140.0),
+                height: Val::Px(36.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BackgroundColor(BUTTON_COLOR),
+            BlocksWorldInput,
+            LevelEntity,
+        ))
+        .with_child((
+            Text::new("run"),
+            TextFont {
+                font: asset_server.load("Fonts/CyberpunkCraftpixPixel.otf"),
+                font_size: 20.,
+                ..default()
+            },
+            TextColor(Color::srgb(211.0 / 255.0, 211.0 / 255.0, 211.0 / 255.0)),
+        ));
+}
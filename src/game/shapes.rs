@@ -0,0 +1,205 @@
+use crate::game::*;
+use bevy::color::palettes::css::RED;
+
+pub const GRID_SIZE: f32 = 40.;
+pub const FUNNEL_OPENING: f32 = 60.; // gap between the funnel walls at the bottom
+
+// shape tools snap to the grid while this is on, toggled with G
+#[derive(Resource, Default)]
+pub struct GridSnap(pub bool);
+
+impl GridSnap {
+    pub fn snap(&self, point: Vec2) -> Vec2 {
+        if self.0 {
+            (point / GRID_SIZE).round() * GRID_SIZE
+        } else {
+            point
+        }
+    }
+}
+
+// One click-drag with the line, rectangle or funnel tool.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Shape {
+    pub tool: Tools,
+    pub start: Vec2,
+    pub end: Vec2,
+}
+
+impl Shape {
+    // what placing the shape would draw, nothing if it is too small
+    pub fn drawings(&self) -> Vec<Drawing> {
+        let rect = Rect::from_corners(self.start, self.end);
+        match self.tool {
+            Tools::Line if self.start.distance(self.end) >= STROKE_SPACING => {
+                vec![Drawing::Stroke(straight_stroke(self.start, self.end))]
+            }
+            Tools::Rectangle
+                if rect.width() >= STROKE_SPACING && rect.height() >= STROKE_SPACING =>
+            {
+                vec![Drawing::Rectangle(rect)]
+            }
+            // the drag spans the top of the funnel, the walls meet the opening at the bottom
+            Tools::Funnel if rect.width() > FUNNEL_OPENING + 2. * STROKE_RADIUS => {
+                let wall_end = FUNNEL_OPENING / 2. + STROKE_RADIUS;
+                let center = rect.center().x;
+                vec![
+                    Drawing::Stroke(straight_stroke(
+                        Vec2::new(rect.min.x, rect.max.y),
+                        Vec2::new(center - wall_end, rect.min.y),
+                    )),
+                    Drawing::Stroke(straight_stroke(
+                        Vec2::new(rect.max.x, rect.max.y),
+                        Vec2::new(center + wall_end, rect.min.y),
+                    )),
+                ]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn ink(&self) -> u32 {
+        self.drawings().iter().map(Drawing::ink).sum()
+    }
+}
+
+fn straight_stroke(from: Vec2, to: Vec2) -> Vec<Vec2> {
+    let mut points = vec![from];
+    extend_stroke(&mut points, to, usize::MAX);
+    points
+}
+
+// the shape being dragged out, placed when the mouse is released
+#[derive(Resource, Default)]
+pub struct ShapeDrag(pub Option<Shape>);
+
+#[derive(Component)]
+pub struct ShapePreview;
+
+// Places the shape as a single undo step. Refused if it takes more ink than is left.
+pub fn place_shape(
+    mut commands: Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    history: &mut EditHistory,
+    ink_left: Option<u32>,
+    shape: Shape,
+) -> bool {
+    let drawings = shape.drawings();
+    let ink: u32 = drawings.iter().map(Drawing::ink).sum();
+    if drawings.is_empty() || ink_left.is_some_and(|left| ink > left) {
+        return false;
+    }
+    for drawing in &drawings {
+        spawn_drawing(commands.reborrow(), meshes, materials, drawing.clone());
+    }
+    history.record_drawn(drawings);
+    true
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn draw_shapes(
+    commands: Commands,
+    buttons: Res<ButtonInput<MouseButton>>,
+    mouse_pos: Res<MouseWorldPosition>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    over_element: Res<OverClickableElement>,
+    dragging: Res<Dragging>,
+    tool_selected: Res<SelectedTool>,
+    grid: Res<GridSnap>,
+    mut drag: ResMut<ShapeDrag>,
+    mut history: ResMut<EditHistory>,
+    ink_budget: Res<InkBudget>,
+    drawn: Query<&DrawnObstacle>,
+) {
+    if !matches!(
+        tool_selected.0,
+        Tools::Line | Tools::Rectangle | Tools::Funnel
+    ) {
+        drag.0 = None;
+        return;
+    }
+    let Some(mouse_position) = mouse_pos.0 else {
+        return;
+    };
+    let point = grid.snap(mouse_position.0);
+
+    // a shape starts like a stroke, not over clickable elements or while dragging
+    if buttons.just_pressed(MouseButton::Left)
+        && over_element.0.is_none()
+        && dragging.entity.is_none()
+    {
+        drag.0 = Some(Shape {
+            tool: tool_selected.0,
+            start: point,
+            end: point,
+        });
+    }
+    let Some(shape) = &mut drag.0 else {
+        return;
+    };
+    shape.end = point;
+    if !buttons.pressed(MouseButton::Left) {
+        let shape = *shape;
+        drag.0 = None;
+        let ink = ink_left(&ink_budget, ink_used(drawn.iter()));
+        place_shape(
+            commands,
+            &mut meshes,
+            &mut materials,
+            &mut history,
+            ink,
+            shape,
+        );
+    }
+}
+
+// shows the dragged shape before it is placed, red if there isn't enough ink for it
+pub fn update_shape_preview(
+    drag: Res<ShapeDrag>,
+    ink_budget: Res<InkBudget>,
+    drawn: Query<&DrawnObstacle>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut preview: Query<
+        (&Mesh2d, &MeshMaterial2d<ColorMaterial>, &mut Visibility),
+        With<ShapePreview>,
+    >,
+) {
+    let (mesh, material, mut visibility) = preview.single_mut();
+    let Some(shape) = drag.0 else {
+        *visibility = Visibility::Hidden;
+        return;
+    };
+    *visibility = Visibility::Visible;
+    if let Some(mesh) = meshes.get_mut(&mesh.0) {
+        *mesh = drawings_mesh(&shape.drawings());
+    }
+    let affordable =
+        ink_left(&ink_budget, ink_used(drawn.iter())).is_none_or(|ink| shape.ink() <= ink);
+    if let Some(material) = materials.get_mut(&material.0) {
+        material.color = if affordable {
+            Color::srgba(0., 0., 0., 0.4)
+        } else {
+            RED.with_alpha(0.4).into()
+        };
+    }
+}
+
+pub fn toggle_grid(keys: Res<ButtonInput<KeyCode>>, mut grid: ResMut<GridSnap>) {
+    if keys.just_pressed(KeyCode::KeyG) {
+        grid.0 = !grid.0;
+    }
+}
+
+pub fn draw_grid(grid: Res<GridSnap>, mut gizmos: Gizmos) {
+    if grid.0 {
+        gizmos.grid_2d(
+            Isometry2d::IDENTITY,
+            UVec2::new(40, 24),
+            Vec2::splat(GRID_SIZE),
+            Color::srgba(1., 1., 1., 0.1),
+        );
+    }
+}
@@ -0,0 +1,232 @@
+use crate::game::*;
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy::ui::RelativeCursorPosition;
+
+const SHOP_ENTRY_HEIGHT: f32 = 128.0;
+const SHOP_SCROLL_LINE: f32 = 40.0; // pixels per scroll wheel step
+const AVAILABLE_COLOR: Color = Color::WHITE;
+const UNAVAILABLE_COLOR: Color = Color::srgb(0.4, 0.4, 0.4);
+
+// Scrollable list on the right side of the screen, one entry per machine in the level's shop.
+#[derive(Component)]
+pub struct ShopPanel;
+
+// a machine in the shop, `slot` is its index in the level file
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct ShopEntry {
+    pub slot: usize,
+    pub machine: Machine,
+}
+
+impl ShopEntry {
+    // affordable, in stock and the shop isn't closed for a run
+    pub fn can_buy(&self, money: i32, stock: &ShopStock, phase: &RunPhase) -> bool {
+        money > self.machine.get_cost() && stock.available(self.slot) && phase.shop_open()
+    }
+}
+
+// the shop slot a placed machine was bought from, selling it puts it back in stock
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct ShopSlot(pub usize);
+
+#[derive(Component)]
+pub struct ShopIcon(pub usize);
+
+#[derive(Component)]
+pub struct ShopStockText(pub usize);
+
+pub fn spawn_shop(mut commands: Commands, machines: &[Machine], asset_server: &Res<AssetServer>) {
+    let font = asset_server.load("Fonts/CyberpunkCraftpixPixel.otf");
+    commands
+        .spawn((
+            ShopPanel,
+            Node {
+                position_type: PositionType::Absolute,
+                right: Val::Px(0.0),
+                top: Val::Px(130.0),
+                bottom: Val::Px(20.0),
+                width: Val::Px(320.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(10.0),
+                overflow: Overflow::scroll_y(),
+                ..default()
+            },
+            ScrollPosition::default(),
+            BlocksWorldInput,
+            LevelEntity,
+        ))
+        .with_children(|panel| {
+            for (slot, machine) in machines.iter().enumerate() {
+                let view = machine.get_sprite_view();
+                panel
+                    .spawn((
+                        Button,
+                        ShopEntry {
+                            slot,
+                            machine: *machine,
+                        },
+                        Node {
+                            height: Val::Px(SHOP_ENTRY_HEIGHT),
+                            flex_shrink: 0.0,
+                            align_items: AlignItems::Center,
+                            padding: UiRect::horizontal(Val::Px(24.0)),
+                            column_gap: Val::Px(16.0),
+                            ..default()
+                        },
+                        ImageNode::new(asset_server.load("UI/machine_window_empty.png")),
+                    ))
+                    .with_children(|entry| {
+                        entry.spawn((
+                            ShopIcon(slot),
+                            ImageNode {
+                                flip_x: view.get_flip_x(),
+                                ..ImageNode::new(asset_server.load(view.get_sprite()))
+                            },
+                            Node {
+                                width: Val::Px(64.0),
+                                flex_shrink: 0.0,
+                                ..default()
+                            },
+                        ));
+                        entry
+                            .spawn(Node {
+                                flex_direction: FlexDirection::Column,
+                                row_gap: Val::Px(4.0),
+                                ..default()
+                            })
+                            .with_children(|text| {
+                                text.spawn((
+                                    Text::new(machine.get_name()),
+                                    TextFont {
+                                        font: font.clone(),
+                                        font_size: 20.,
+                                        ..default()
+                                    },
+                                    TextColor(Color::srgb(
+                                        211.0 / 255.0,
+                                        211.0 / 255.0,
+                                        211.0 / 255.0,
+                                    )),
+                                ));
+                                text.spawn((
+                                    Text::new(machine.get_description()),
+                                    TextFont {
+                                        font: font.clone(),
+                                        font_size: 12.,
+                                        ..default()
+                                    },
+                                    TextColor(Color::srgb(
+                                        211.0 / 255.0,
+                                        211.0 / 255.0,
+                                        211.0 / 255.0,
+                                    )),
+                                ));
+                                text.spawn((
+                                    Text::new(format!("{}", machine.get_cost())),
+                                    TextFont {
+                                        font: font.clone(),
+                                        font_size: 30.,
+                                        ..default()
+                                    },
+                                    TextColor(Color::srgb(1.0, 130.0 / 255.0, 130.0 / 255.0)),
+                                ));
+                                text.spawn((
+                                    ShopStockText(slot),
+                                    Text::new(""),
+                                    TextFont {
+                                        font: font.clone(),
+                                        font_size: 12.,
+                                        ..default()
+                                    },
+                                    TextColor(Color::srgb(
+                                        211.0 / 255.0,
+                                        211.0 / 255.0,
+                                        211.0 / 255.0,
+                                    )),
+                                ));
+                            });
+                    });
+            }
+        });
+}
+
+// clicking an available machine starts placing it, see `place_machine`
+#[allow(clippy::too_many_arguments)]
+pub fn buy_machines(
+    entries: Query<(&Interaction, &ShopEntry), Changed<Interaction>>,
+    wallet: Wallet,
+    stock: Res<ShopStock>,
+    phase: Res<RunPhase>,
+    mouse_pos: Res<MouseWorldPosition>,
+    mut placement: ResMut<MachinePlacement>,
+    mut selected: ResMut<SelectedTool>,
+    ghosts: Query<Entity, With<PlacementGhost>>,
+    mut commands: Commands,
+) {
+    for (interaction, entry) in entries.iter() {
+        if *interaction == Interaction::Pressed && entry.can_buy(wallet.balance(), &stock, &phase) {
+            // placing is done with the mouse tool
+            selected.0 = Tools::Mouse;
+            start_placement(
+                commands.reborrow(),
+                &mut placement,
+                &ghosts,
+                *entry,
+                mouse_pos.0.unwrap_or(Position(Vec2::ZERO)),
+            );
+        }
+    }
+}
+
+// greys out machines that can't be afforded, are sold out or can't be bought during a run
+pub fn update_shop_entries(
+    wallet: Wallet,
+    stock: Res<ShopStock>,
+    phase: Res<RunPhase>,
+    mut entries: Query<(&ShopEntry, &mut ImageNode)>,
+    mut icons: Query<(&ShopIcon, &mut ImageNode), Without<ShopEntry>>,
+    mut stock_texts: Query<(&ShopStockText, &mut Text)>,
+) {
+    let money = wallet.balance();
+    let available = |entry: &ShopEntry| entry.can_buy(money, &stock, &phase);
+    let mut slot_colors = Vec::new();
+    for (entry, mut image) in entries.iter_mut() {
+        let color = if available(entry) {
+            AVAILABLE_COLOR
+        } else {
+            UNAVAILABLE_COLOR
+        };
+        image.color = color;
+        slot_colors.push((entry.slot, color));
+    }
+    for (icon, mut image) in icons.iter_mut() {
+        if let Some((_, color)) = slot_colors.iter().find(|(slot, _)| *slot == icon.0) {
+            image.color = *color;
+        }
+    }
+    for (stock_text, mut text) in stock_texts.iter_mut() {
+        text.0 = match stock.0.get(stock_text.0).copied().flatten() {
+            Some(0) => "sold out".to_string(),
+            Some(left) => format!("{} left", left),
+            None => String::new(),
+        };
+    }
+}
+
+pub fn scroll_shop(
+    mut scroll: EventReader<MouseWheel>,
+    mut panels: Query<(&RelativeCursorPosition, &mut ScrollPosition), With<ShopPanel>>,
+) {
+    for event in scroll.read() {
+        let pixels = match event.unit {
+            MouseScrollUnit::Line => event.y * SHOP_SCROLL_LINE,
+            MouseScrollUnit::Pixel => event.y,
+        };
+        for (cursor, mut scroll_position) in panels.iter_mut() {
+            if cursor.mouse_over() {
+                // clamped to the content by the ui layout
+                scroll_position.offset_y = (scroll_position.offset_y - pixels).max(0.0);
+            }
+        }
+    }
+}
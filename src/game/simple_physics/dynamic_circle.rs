@@ -2,13 +2,94 @@ use crate::game::*;
 use bevy::prelude::*;
 
 const RESTITUTION: f32 = 0.6; // Coefficient of restitution: 0.0 (perfectly inelastic) to 1.0 (perfectly elastic)
+const DYNAMIC_RESTITUTION: f32 = 0.2; // lower than against static geometry, so items settle into stacks instead of bouncing apart
+const CORRECTION_PERCENT: f32 = 0.8; // share of the overlap resolved per frame, the rest is left for the next frames to avoid jitter
+const CORRECTION_SLOP: f32 = 0.5; // overlap (in pixels) that is tolerated without positional correction
+
+pub fn collision_between_dynamic_circles(
+    mut dynamic_circles_query: Query<(
+        Entity,
+        &mut Position,
+        &mut Velocity,
+        &CirclePhysics,
+        Option<&Mass>,
+    )>,
+    broadphase: Res<Broadphase>,
+    mut candidates: Local<Vec<Entity>>,
+) {
+    let circles: Vec<(Entity, Rect)> = dynamic_circles_query
+        .iter()
+        .map(|(entity, pos, _, circle, _)| (entity, circle_bounds(pos.0, circle)))
+        .collect();
+
+    for (entity_a, bounds_a) in circles {
+        broadphase.dynamic_circles.query(bounds_a, &mut candidates);
+        for &entity_b in candidates.iter() {
+            // every pair shows up from both sides, only resolve it once
+            if entity_b <= entity_a {
+                continue;
+            }
+            let Ok(
+                [(_, mut pos_a, mut vel_a, circle_a, mass_a), (_, mut pos_b, mut vel_b, circle_b, mass_b)],
+            ) = dynamic_circles_query.get_many_mut([entity_a, entity_b])
+            else {
+                continue;
+            };
+
+            let distance_vec = pos_b.0 - pos_a.0;
+            let distance = distance_vec.length();
+            let sum_radii = circle_a.radius + circle_b.radius;
+
+            // Check for collision
+            if distance >= sum_radii {
+                continue;
+            }
+
+            // Normal pointing from a to b. Items spawned at the same point get pushed apart sideways.
+            let normal = if distance == 0.0 {
+                Vec2::X
+            } else {
+                distance_vec / distance
+            };
+
+            let inverse_mass_a = 1.0 / mass_a.map_or(circle_a.default_mass(), |mass| mass.0);
+            let inverse_mass_b = 1.0 / mass_b.map_or(circle_b.default_mass(), |mass| mass.0);
+            let inverse_mass_sum = inverse_mass_a + inverse_mass_b;
+
+            // 1. Positional correction: move both circles apart, the lighter one moves further
+            let overlap = sum_radii - distance;
+            let correction = normal
+                * ((overlap - CORRECTION_SLOP).max(0.0) * CORRECTION_PERCENT / inverse_mass_sum);
+            pos_a.0 -= correction * inverse_mass_a;
+            pos_b.0 += correction * inverse_mass_b;
+
+            // 2. Impulse: exchange momentum along the normal
+            let relative_velocity = vel_b.0 - vel_a.0;
+            let velocity_along_normal = relative_velocity.dot(normal);
+
+            // Only resolve if objects are moving towards each other
+            if velocity_along_normal < 0.0 {
+                let impulse =
+                    -(1.0 + DYNAMIC_RESTITUTION) * velocity_along_normal / inverse_mass_sum;
+                vel_a.0 -= normal * impulse * inverse_mass_a;
+                vel_b.0 += normal * impulse * inverse_mass_b;
+            }
+        }
+    }
+}
 
 pub fn collision_with_static_circles(
     mut dynamic_circles_query: Query<(&mut Position, &mut Velocity, &CirclePhysics)>,
     static_circles_query: Query<(&Position, &CirclePhysics), Without<Velocity>>,
+    broadphase: Res<Broadphase>,
+    mut candidates: Local<Vec<Entity>>,
 ) {
     for (mut dyn_pos, mut dyn_vel, dyn_circle) in dynamic_circles_query.iter_mut() {
-        for (static_pos, static_circle) in static_circles_query.iter() {
+        broadphase
+            .static_colliders
+            .query(circle_bounds(dyn_pos.0, dyn_circle), &mut candidates);
+        // candidates that aren't static circles (e.g. machine walls) are skipped by the query
+        for (static_pos, static_circle) in static_circles_query.iter_many(candidates.iter()) {
             let distance_vec = dyn_pos.0 - static_pos.0;
             let distance = distance_vec.length();
             let sum_radii = dyn_circle.radius + static_circle.radius;
@@ -46,34 +127,104 @@ pub fn collision_with_static_circles(
     }
 }
 
+pub fn collision_with_static_strokes(
+    mut dynamic_circles_query: Query<(&mut Position, &mut Velocity, &CirclePhysics)>,
+    static_strokes_query: Query<(&Position, &StrokePhysics), Without<Velocity>>,
+    broadphase: Res<Broadphase>,
+    mut candidates: Local<Vec<Entity>>,
+) {
+    for (mut dyn_pos, mut dyn_vel, dyn_circle) in dynamic_circles_query.iter_mut() {
+        broadphase
+            .static_colliders
+            .query(circle_bounds(dyn_pos.0, dyn_circle), &mut candidates);
+        for (stroke_pos, stroke) in static_strokes_query.iter_many(candidates.iter()) {
+            // each capsule is a static circle sliding along its segment
+            for (start, end) in stroke.segments() {
+                let (start, end) = (stroke_pos.0 + start, stroke_pos.0 + end);
+                let segment = end - start;
+                let along = if segment == Vec2::ZERO {
+                    0.0
+                } else {
+                    ((dyn_pos.0 - start).dot(segment) / segment.length_squared()).clamp(0.0, 1.0)
+                };
+                let closest_point = start + segment * along;
+
+                let distance_vec = dyn_pos.0 - closest_point;
+                let distance = distance_vec.length();
+                let sum_radii = dyn_circle.radius + stroke.radius;
+                if distance >= sum_radii {
+                    continue;
+                }
+
+                // 1. Separation, sideways if the center lies exactly on the segment
+                let normal = if distance == 0.0 {
+                    segment.perp().try_normalize().unwrap_or(Vec2::Y)
+                } else {
+                    distance_vec / distance
+                };
+                dyn_pos.0 += normal * (sum_radii - distance);
+
+                // 2. Reflection
+                let velocity_along_normal = dyn_vel.0.dot(normal);
+                if velocity_along_normal < 0.0 {
+                    let impulse = -(1.0 + RESTITUTION) * velocity_along_normal;
+                    dyn_vel.0 += normal * impulse;
+                }
+            }
+        }
+    }
+}
+
+// machines, conveyor belts and drawn rectangles
+type StaticRectangles<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Position,
+        &'static ColliderCollection,
+        Option<&'static SurfaceVelocity>,
+        Option<&'static Rotation>,
+    ),
+    Without<Velocity>,
+>;
+
 pub fn collision_with_static_rectangles(
     mut dynamic_circles_query: Query<(&mut Position, &mut Velocity, &CirclePhysics)>,
-    static_rectangles_query: Query<(&Position, &ColliderCollection), Without<Velocity>>,
+    static_rectangles_query: StaticRectangles,
+    broadphase: Res<Broadphase>,
+    mut candidates: Local<Vec<Entity>>,
 ) {
     for (mut dyn_pos, mut dyn_vel, dyn_circle) in dynamic_circles_query.iter_mut() {
-        for (rect_pos, collider_collection) in static_rectangles_query.iter() {
+        broadphase
+            .static_colliders
+            .query(circle_bounds(dyn_pos.0, dyn_circle), &mut candidates);
+        for (rect_pos, collider_collection, surface_velocity, rotation) in
+            static_rectangles_query.iter_many(candidates.iter())
+        {
+            let rotation = rotation.map_or(Rot2::IDENTITY, Rotation::rot2);
             for rect in &collider_collection.0 {
+                // the circle's center in the unturned frame of the collection, where the rectangles are axis-aligned
+                let local_pos = rotation.inverse() * (dyn_pos.0 - rect_pos.0);
+
                 // Calculate rectangle's half-dimensions
                 let half_width = rect.width / 2.0;
                 let half_height = rect.height / 2.0;
 
-                let rect_pos_x = rect_pos.0.x + rect.offset_x;
-                let rect_pos_y = rect_pos.0.y + rect.offset_y;
+                let rect_pos_x = rect.offset_x;
+                let rect_pos_y = rect.offset_y;
 
                 // Find the closest point on the rectangle to the circle's center
-                let closest_x = dyn_pos
-                    .0
+                let closest_x = local_pos
                     .x
                     .clamp(rect_pos_x - half_width, rect_pos_x + half_width);
-                let closest_y = dyn_pos
-                    .0
+                let closest_y = local_pos
                     .y
                     .clamp(rect_pos_y - half_height, rect_pos_y + half_height);
 
                 let closest_point = Vec2::new(closest_x, closest_y);
 
                 // Vector from the closest point on rectangle to circle center
-                let distance_vec = dyn_pos.0 - closest_point;
+                let distance_vec = local_pos - closest_point;
                 let distance = distance_vec.length();
 
                 // Check for collision
@@ -82,7 +233,8 @@ pub fn collision_with_static_rectangles(
 
                     // 1. Separation: Move the dynamic circle out of the rectangle
                     let overlap = dyn_circle.radius - distance;
-                    let normal: Vec2;
+                    let mut normal: Vec2;
+                    let local_normal: Vec2;
 
                     // Handle the edge case where the circle's center is exactly at the closest point
                     // (i.e., the circle's center is inside the rectangle).
@@ -90,10 +242,10 @@ pub fn collision_with_static_rectangles(
                     // We determine the normal by finding the axis with the minimum overlap.
                     if distance == 0.0 {
                         // Calculate penetration depths on each axis
-                        let dx_min = dyn_pos.0.x - (rect_pos_x - half_width); // Distance to left edge of rect
-                        let dx_max = (rect_pos_x + half_width) - dyn_pos.0.x; // Distance to right edge of rect
-                        let dy_min = dyn_pos.0.y - (rect_pos_y - half_height); // Distance to bottom edge of rect
-                        let dy_max = (rect_pos_y + half_height) - dyn_pos.0.y; // Distance to top edge of rect
+                        let dx_min = local_pos.x - (rect_pos_x - half_width); // Distance to left edge of rect
+                        let dx_max = (rect_pos_x + half_width) - local_pos.x; // Distance to right edge of rect
+                        let dy_min = local_pos.y - (rect_pos_y - half_height); // Distance to bottom edge of rect
+                        let dy_max = (rect_pos_y + half_height) - local_pos.y; // Distance to top edge of rect
 
                         // Find the smallest penetration depth
                         let min_x_overlap = dx_min.min(dx_max);
@@ -117,9 +269,12 @@ pub fn collision_with_static_rectangles(
                         // Since distance was 0, it means the circle is fully inside.
                         // We push it out by its radius plus a small margin (or just radius for simplicity)
                         // This is a heuristic for when the circle spawns inside.
+                        local_normal = normal;
+                        normal = rotation * normal;
                         dyn_pos.0 += normal * dyn_circle.radius;
                     } else {
-                        normal = distance_vec.normalize();
+                        local_normal = distance_vec.normalize();
+                        normal = rotation * local_normal;
                         dyn_pos.0 += normal * overlap;
                     }
 
@@ -131,6 +286,16 @@ pub fn collision_with_static_rectangles(
                         let impulse = -(1.0 + RESTITUTION) * velocity_along_normal;
                         dyn_vel.0 += normal * impulse;
                     }
+
+                    // 3. Moving surface: items on top take on its speed along the surface,
+                    // the ends and the underside are plain walls
+                    let on_top = local_normal.x == 0.0 && local_normal.y > 0.0;
+                    if let Some(surface_velocity) = surface_velocity.filter(|_| on_top) {
+                        // the top carries to the right
+                        let tangent = Vec2::new(normal.y, -normal.x);
+                        let velocity_along_tangent = dyn_vel.0.dot(tangent);
+                        dyn_vel.0 += tangent * (surface_velocity.0 - velocity_along_tangent);
+                    }
                 }
             }
         }
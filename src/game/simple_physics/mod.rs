@@ -2,5 +2,7 @@ pub mod dynamic_circle;
 pub use dynamic_circle::*;
 pub mod physics_objects;
 pub use physics_objects::*;
+pub mod spatial_hash;
+pub use spatial_hash::*;
 pub mod update_position;
 pub use update_position::*;
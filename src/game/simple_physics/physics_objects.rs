@@ -10,6 +10,26 @@ pub struct CirclePhysics {
 #[derive(Component)]
 pub struct ColliderCollection(pub Vec<RectanglePhysics>);
 
+// A chain of capsules through `points` (relative to the position), e.g. a drawn stroke.
+// A single point is a circle.
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct StrokePhysics {
+    pub points: Vec<Vec2>,
+    pub radius: f32,
+}
+
+impl StrokePhysics {
+    // pairs of consecutive points, a single point is a segment of length zero
+    pub fn segments(&self) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+        let ends = if self.points.len() == 1 {
+            &self.points[..]
+        } else {
+            self.points.get(1..).unwrap_or_default()
+        };
+        self.points.iter().copied().zip(ends.iter().copied())
+    }
+}
+
 #[derive(Component)]
 pub struct RectanglePhysics {
     pub width: f32,
@@ -29,8 +49,38 @@ impl Default for RectanglePhysics {
     }
 }
 
+// Speed along the surface of a static collider, e.g. a conveyor belt.
+// Positive values carry items lying on top to the right.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct SurfaceVelocity(pub f32);
+
 #[derive(Component, Debug, Clone, Copy, PartialEq)]
 pub struct Position(pub Vec2);
 
+// Counter-clockwise angle in radians. Static colliders turn around their position,
+// offsets included.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Default)]
+pub struct Rotation(pub f32);
+
+impl Rotation {
+    pub fn rot2(&self) -> Rot2 {
+        Rot2::radians(self.0)
+    }
+}
+
 #[derive(Component)]
 pub struct Velocity(pub Vec2);
+
+// Position of a dynamic object at the start of the last physics tick, used to interpolate its transform
+#[derive(Component)]
+pub struct PreviousPosition(pub Vec2);
+
+// Optional for dynamic objects, circles without it weigh as much as their area
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct Mass(pub f32);
+
+impl CirclePhysics {
+    pub fn default_mass(&self) -> f32 {
+        std::f32::consts::PI * self.radius * self.radius
+    }
+}
@@ -0,0 +1,216 @@
+use crate::game::*;
+use bevy::utils::HashMap;
+
+// Edge length of a grid cell in pixels. A few item diameters, so most objects only touch one to four cells.
+pub const BROADPHASE_CELL_SIZE: f32 = 64.0;
+
+/*
+Uniform grid over the game space, mapping each cell to the entities whose bounding box overlaps it.
+Used as broadphase: the collision systems only run the exact (narrowphase) checks against
+entities that share a cell with the object being tested.
+*/
+pub struct SpatialHash {
+    cell_size: f32,
+    cells: HashMap<IVec2, Vec<Entity>>,
+    bounds: HashMap<Entity, (IVec2, IVec2)>, // covered cell range, needed to remove an entity again
+}
+
+impl SpatialHash {
+    pub fn new(cell_size: f32) -> Self {
+        SpatialHash {
+            cell_size,
+            cells: HashMap::default(),
+            bounds: HashMap::default(),
+        }
+    }
+
+    fn cell_range(&self, aabb: Rect) -> (IVec2, IVec2) {
+        (
+            (aabb.min / self.cell_size).floor().as_ivec2(),
+            (aabb.max / self.cell_size).floor().as_ivec2(),
+        )
+    }
+
+    // inserts the entity, or moves it if it is already in the grid
+    pub fn insert(&mut self, entity: Entity, aabb: Rect) {
+        let range = self.cell_range(aabb);
+        if self.bounds.get(&entity) == Some(&range) {
+            return;
+        }
+        self.remove(entity);
+        for x in range.0.x..=range.1.x {
+            for y in range.0.y..=range.1.y {
+                self.cells.entry(IVec2::new(x, y)).or_default().push(entity);
+            }
+        }
+        self.bounds.insert(entity, range);
+    }
+
+    pub fn remove(&mut self, entity: Entity) {
+        let Some((min, max)) = self.bounds.remove(&entity) else {
+            return;
+        };
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                let cell = IVec2::new(x, y);
+                if let Some(entities) = self.cells.get_mut(&cell) {
+                    entities.retain(|other| *other != entity);
+                    if entities.is_empty() {
+                        self.cells.remove(&cell);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+        self.bounds.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.bounds.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bounds.is_empty()
+    }
+
+    // replaces the content of `candidates` with every entity that might overlap `aabb`, each listed once
+    pub fn query(&self, aabb: Rect, candidates: &mut Vec<Entity>) {
+        candidates.clear();
+        let (min, max) = self.cell_range(aabb);
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                if let Some(entities) = self.cells.get(&IVec2::new(x, y)) {
+                    candidates.extend_from_slice(entities);
+                }
+            }
+        }
+        // objects spanning several cells are found once per cell
+        if min != max {
+            candidates.sort_unstable();
+            candidates.dedup();
+        }
+    }
+}
+
+// Static geometry only moves when it is dragged, so it is updated incrementally.
+// Dynamic circles move every frame and are re-inserted each frame.
+#[derive(Resource)]
+pub struct Broadphase {
+    pub static_colliders: SpatialHash,
+    pub dynamic_circles: SpatialHash,
+}
+
+impl Default for Broadphase {
+    fn default() -> Self {
+        Broadphase {
+            static_colliders: SpatialHash::new(BROADPHASE_CELL_SIZE),
+            dynamic_circles: SpatialHash::new(BROADPHASE_CELL_SIZE),
+        }
+    }
+}
+
+pub fn circle_bounds(position: Vec2, circle: &CirclePhysics) -> Rect {
+    Rect::from_center_half_size(position, Vec2::splat(circle.radius))
+}
+
+pub fn collider_collection_bounds(
+    position: Vec2,
+    colliders: &ColliderCollection,
+    rotation: Rot2,
+) -> Rect {
+    colliders
+        .0
+        .iter()
+        .map(|rect| {
+            // the box around a turned rectangle
+            let (sin, cos) = (rotation.sin.abs(), rotation.cos.abs());
+            Rect::from_center_size(
+                position + rotation * Vec2::new(rect.offset_x, rect.offset_y),
+                Vec2::new(
+                    cos * rect.width + sin * rect.height,
+                    sin * rect.width + cos * rect.height,
+                ),
+            )
+        })
+        .fold(Rect::EMPTY, |bounds, rect| bounds.union(rect))
+}
+
+pub fn stroke_bounds(position: Vec2, stroke: &StrokePhysics) -> Rect {
+    stroke
+        .points
+        .iter()
+        .map(|point| Rect::from_center_half_size(position + *point, Vec2::splat(stroke.radius)))
+        .fold(Rect::EMPTY, |bounds, rect| bounds.union(rect))
+}
+
+// Runs every frame, not just on physics ticks: removals are only reported for a couple of frames,
+// and colliders are erased or sold while the physics stand still.
+pub fn prune_broadphase(
+    mut broadphase: ResMut<Broadphase>,
+    mut removed_positions: RemovedComponents<Position>,
+) {
+    for entity in removed_positions.read() {
+        broadphase.static_colliders.remove(entity);
+    }
+}
+
+// static colliders that were spawned, moved, turned or resized since the last frame
+type ChangedStaticCollider = (
+    Without<Velocity>,
+    Or<(
+        With<CirclePhysics>,
+        With<ColliderCollection>,
+        With<StrokePhysics>,
+    )>,
+    Or<(
+        Changed<Position>,
+        Changed<CirclePhysics>,
+        Changed<ColliderCollection>,
+        Changed<StrokePhysics>,
+        Changed<Rotation>,
+    )>,
+);
+
+pub fn update_broadphase(
+    mut broadphase: ResMut<Broadphase>,
+    changed_static: Query<Entity, ChangedStaticCollider>,
+    static_circles: Query<(&Position, &CirclePhysics), Without<Velocity>>,
+    static_rectangles: Query<
+        (&Position, &ColliderCollection, Option<&Rotation>),
+        Without<Velocity>,
+    >,
+    static_strokes: Query<(&Position, &StrokePhysics), Without<Velocity>>,
+    dynamic_circles: Query<(Entity, &Position, &CirclePhysics), With<Velocity>>,
+) {
+    let broadphase = broadphase.as_mut();
+
+    for entity in changed_static.iter() {
+        let mut bounds = Rect::EMPTY;
+        if let Ok((pos, circle)) = static_circles.get(entity) {
+            bounds = bounds.union(circle_bounds(pos.0, circle));
+        }
+        if let Ok((pos, colliders, rotation)) = static_rectangles.get(entity) {
+            let rotation = rotation.map_or(Rot2::IDENTITY, Rotation::rot2);
+            bounds = bounds.union(collider_collection_bounds(pos.0, colliders, rotation));
+        }
+        if let Ok((pos, stroke)) = static_strokes.get(entity) {
+            bounds = bounds.union(stroke_bounds(pos.0, stroke));
+        }
+        if bounds.is_empty() {
+            // e.g. an empty collider collection
+            broadphase.static_colliders.remove(entity);
+        } else {
+            broadphase.static_colliders.insert(entity, bounds);
+        }
+    }
+
+    broadphase.dynamic_circles.clear();
+    for (entity, pos, circle) in dynamic_circles.iter() {
+        broadphase
+            .dynamic_circles
+            .insert(entity, circle_bounds(pos.0, circle));
+    }
+}
@@ -17,10 +17,52 @@ pub fn update_position(mut query: Query<(&mut Position, &Velocity)>, time: Res<T
     }
 }
 
-pub fn on_changed_position(
-    mut changed_pos: Query<(&Position, &SpriteView, &mut Transform), Changed<Position>>,
+// remember where each dynamic object was before this tick moves it
+pub fn store_previous_position(mut query: Query<(&Position, &mut PreviousPosition)>) {
+    for (pos, mut previous_pos) in query.iter_mut() {
+        previous_pos.0 = pos.0;
+    }
+}
+
+pub fn on_add_velocity(
+    trigger: Trigger<OnAdd, Velocity>,
+    positions: Query<&Position>,
+    mut commands: Commands,
 ) {
-    for (pos, view, mut transform) in changed_pos.iter_mut() {
-        transform.translation = Vec3::new(pos.0.x, pos.0.y, view.get_layer());
+    let entity = trigger.entity();
+    if let Ok(pos) = positions.get(entity) {
+        commands.entity(entity).insert(PreviousPosition(pos.0));
+    }
+}
+
+// static objects only when they were moved, dynamic objects every frame
+type NeedsRedraw = Or<(Changed<Position>, Changed<Rotation>, With<PreviousPosition>)>;
+
+type Redrawn<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Position,
+        Option<&'static PreviousPosition>,
+        &'static SpriteView,
+        Option<&'static Rotation>,
+        &'static mut Transform,
+    ),
+    NeedsRedraw,
+>;
+
+// Runs every frame. Dynamic objects are drawn between their last two physics ticks,
+// so movement looks smooth when the frame rate and the tick rate differ.
+pub fn on_changed_position(mut changed_pos: Redrawn, fixed_time: Res<Time<Fixed>>) {
+    let alpha = fixed_time.overstep_fraction();
+    for (pos, previous_pos, view, rotation, mut transform) in changed_pos.iter_mut() {
+        let drawn_pos = match previous_pos {
+            Some(previous_pos) => previous_pos.0.lerp(pos.0, alpha),
+            None => pos.0,
+        };
+        transform.translation = Vec3::new(drawn_pos.x, drawn_pos.y, view.get_layer());
+        if let Some(rotation) = rotation {
+            transform.rotation = Quat::from_rotation_z(rotation.0);
+        }
     }
 }
@@ -0,0 +1,595 @@
+use crate::game::*;
+use bevy::ecs::system::RunSystemOnce;
+use bevy::input::keyboard::{Key, KeyboardInput, NativeKey};
+use bevy::input::{ButtonState, InputPlugin};
+use bevy::state::app::StatesPlugin;
+use bevy::time::TimeUpdateStrategy;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+// Physics and gameplay ticks per second. Rendered frames in between are interpolated.
+pub const DEFAULT_TICK_RATE: f64 = 60.0;
+
+// Everything needed to play a level: level loading, physics, pipes, machines and the win/loss check.
+// Rendering, audio and mouse input are added on top of this by `main`.
+// Physics and gameplay run in `FixedUpdate`, so a layout behaves the same at any frame rate,
+// and only in `LevelState::Playing`, so they stop while the level is paused or over.
+// Requires the `StatesPlugin` (part of `DefaultPlugins`).
+pub struct SimulationPlugin {
+    pub tick_rate: f64, // fixed ticks per second
+    pub seed: u64,      // seed for `SimulationRng`
+}
+
+impl Default for SimulationPlugin {
+    fn default() -> Self {
+        SimulationPlugin {
+            tick_rate: DEFAULT_TICK_RATE,
+            seed: 0,
+        }
+    }
+}
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Time::<Fixed>::from_hz(self.tick_rate))
+            .insert_resource(SimulationRng::new(self.seed))
+            .insert_resource(LevelRegistry::load())
+            .insert_resource(CurrentLevel(0))
+            .insert_resource(CurrentMoney(-1))
+            .insert_resource(MoneyGoal(0))
+            .init_resource::<InkBudget>()
+            .insert_resource(RefundPercent(DEFAULT_REFUND_PERCENT))
+            .init_resource::<SellConfirmation>()
+            .init_resource::<MachinePlacement>()
+            .init_resource::<ShopStock>()
+            .init_resource::<TimeScale>()
+            .init_resource::<ScaleBeforePause>()
+            .init_resource::<RunPhase>()
+            .init_resource::<IncomeMeter>()
+            .init_resource::<MoneyLedger>()
+            .init_resource::<AppliedMoneyChanges>()
+            .add_event::<MoneyChanged>()
+            .insert_resource(SelectedTool(Tools::Mouse))
+            .insert_resource(SimulationStats::default())
+            .insert_resource(Broadphase::default())
+            .init_resource::<EditHistory>()
+            .init_resource::<ShapeDrag>()
+            .init_resource::<GridSnap>()
+            .add_observer(on_add_view)
+            .add_observer(on_add_output_pipe)
+            .add_observer(on_add_input_pipe)
+            .add_observer(on_add_velocity)
+            .init_state::<AppState>()
+            .add_sub_state::<LevelState>()
+            .enable_state_scoped_entities::<AppState>()
+            .enable_state_scoped_entities::<LevelState>()
+            .add_systems(
+                OnEnter(LevelState::Loading),
+                (
+                    unload_level,
+                    load_level,
+                    reset_time_scale,
+                    reset_income_meter,
+                    clear_money_ledger,
+                )
+                    .chain(),
+            )
+            // back in the menu, nothing of the level may stay on screen or clickable
+            .add_systems(OnExit(AppState::InLevel), unload_level)
+            .add_systems(OnEnter(LevelState::Won), show_level_won)
+            .add_systems(OnEnter(LevelState::Lost), show_level_lost)
+            .add_systems(OnEnter(LevelState::Paused), (show_paused, pause_time_scale))
+            .add_systems(OnExit(LevelState::Paused), resume_time_scale)
+            // the fixed clock keeps running outside of `Playing`, items would be drawn between two ticks
+            .add_systems(OnExit(LevelState::Playing), store_previous_position)
+            .add_systems(
+                FixedUpdate,
+                (
+                    store_previous_position,
+                    add_gravity,
+                    update_broadphase,
+                    collision_between_dynamic_circles,
+                    collision_with_static_circles,
+                    collision_with_static_strokes,
+                    collision_with_static_rectangles,
+                    update_position,
+                )
+                    .chain()
+                    .run_if(in_state(LevelState::Playing)),
+            )
+            .add_systems(
+                FixedUpdate,
+                count_level_ticks.run_if(in_state(LevelState::Playing)),
+            )
+            // chained as well: systems sharing the rng must draw from it in the same order every run
+            .add_systems(
+                FixedUpdate,
+                (
+                    input_pipe_spawn_item,
+                    output_pipe_consume_item,
+                    remove_escaped_items,
+                    one_to_one_crafter,
+                    two_to_one_crafter,
+                    duplicator,
+                    sorter,
+                )
+                    .chain()
+                    .after(update_position)
+                    .run_if(in_state(LevelState::Playing)),
+            )
+            .add_systems(
+                Update,
+                (
+                    apply_time_scale,
+                    update_floating_texts,
+                    record_transactions,
+                    on_changed_position,
+                    prune_broadphase,
+                    follow_relative_position,
+                    update_progress_bars.after(follow_relative_position),
+                    apply_money_changes.before(check_level_end),
+                    check_level_end.run_if(in_state(LevelState::Playing)),
+                    undo_redo.run_if(in_state(LevelState::Playing)),
+                    cancel_placement
+                        .after(pause_level)
+                        .run_if(in_state(LevelState::Playing)),
+                    pause_level
+                        .run_if(in_state(LevelState::Playing).or(in_state(LevelState::Paused))),
+                    continue_after_level_end
+                        .run_if(in_state(LevelState::Won).or(in_state(LevelState::Lost))),
+                ),
+            );
+    }
+}
+
+// The only source of randomness in the simulation. Reset to its seed whenever a level is loaded,
+// so the same player actions replay a level exactly.
+#[derive(Resource, Deref, DerefMut)]
+pub struct SimulationRng {
+    seed: u64,
+    #[deref]
+    rng: StdRng,
+}
+
+impl SimulationRng {
+    pub fn new(seed: u64) -> Self {
+        SimulationRng {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.rng = StdRng::seed_from_u64(self.seed);
+    }
+}
+
+// Item counters for the current level, reset whenever a new level is loaded.
+#[derive(Resource, Default, Debug, Clone, Copy)]
+pub struct SimulationStats {
+    pub ticks: u32,          // fixed ticks spent in `LevelState::Playing`
+    pub items_spawned: u32,  // by input pipes
+    pub items_consumed: u32, // by output pipes
+    pub items_escaped: u32,  // fell out of the game space
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SimulationReport {
+    pub ticks: u32,
+    pub money: i32,
+    pub money_goal: i32,
+    pub items_spawned: u32,
+    pub items_consumed: u32,
+    pub items_escaped: u32,
+    pub won: bool,
+    pub lost: bool,
+}
+
+pub fn count_level_ticks(mut stats: ResMut<SimulationStats>) {
+    stats.ticks += 1;
+}
+
+/*
+Runs a level without a window, audio or user input. Every update advances time by exactly one fixed tick.
+Player actions (clicking pipes, buying machines, drawing) are done through the methods below.
+*/
+pub struct HeadlessSimulation {
+    pub app: App,
+    ticks: u32,
+}
+
+impl HeadlessSimulation {
+    pub fn new(level: u32) -> Self {
+        Self::with_plugin(level, SimulationPlugin::default())
+    }
+
+    pub fn with_plugin(level: u32, plugin: SimulationPlugin) -> Self {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            InputPlugin,
+            StatesPlugin,
+            plugin,
+        ))
+        // asset types the level entities hold handles to, normally registered by `DefaultPlugins`
+        .init_asset::<Image>()
+        .init_asset::<Font>()
+        .init_asset::<AudioSource>()
+        .init_asset::<Mesh>()
+        .init_asset::<ColorMaterial>()
+        .insert_resource(CurrentLevel(level))
+        // the mouse, normally tracked by `main`
+        .insert_resource(MouseWorldPosition(None))
+        .insert_resource(OverClickableElement(None))
+        .insert_resource(Dragging {
+            entity: None,
+            offset_pos: None,
+        });
+        // the draw tool's cursor, normally spawned by `setup_ui`
+        app.world_mut().spawn((DrawIndicator, Transform::default()));
+        // skip the menu, the first frame loads the level
+        app.world_mut()
+            .resource_mut::<NextState<AppState>>()
+            .set(AppState::InLevel);
+        let tick = app.world().resource::<Time<Fixed>>().timestep();
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(tick));
+        app.finish();
+        app.cleanup();
+
+        // run the startup systems and the state transitions, which load the level
+        app.update();
+
+        HeadlessSimulation { app, ticks: 0 }
+    }
+
+    pub fn step(&mut self, ticks: u32) {
+        for _ in 0..ticks {
+            self.app.update();
+            self.ticks += 1;
+        }
+    }
+
+    // steps until the level is won or lost, or `max_ticks` have passed
+    pub fn run(&mut self, max_ticks: u32) -> SimulationReport {
+        for _ in 0..max_ticks {
+            let report = self.report();
+            if report.won || report.lost {
+                return report;
+            }
+            self.step(1);
+        }
+        self.report()
+    }
+
+    pub fn report(&self) -> SimulationReport {
+        let world = self.app.world();
+        let stats = world.resource::<SimulationStats>();
+        let level_state = world
+            .get_resource::<State<LevelState>>()
+            .map(|state| *state.get());
+        SimulationReport {
+            ticks: self.ticks,
+            money: world.resource::<CurrentMoney>().0,
+            money_goal: world.resource::<MoneyGoal>().0,
+            items_spawned: stats.items_spawned,
+            items_consumed: stats.items_consumed,
+            items_escaped: stats.items_escaped,
+            won: level_state == Some(LevelState::Won),
+            lost: level_state == Some(LevelState::Lost),
+        }
+    }
+
+    // same as clicking every input pipe that isn't in the given state yet
+    pub fn set_input_pipes_enabled(&mut self, enabled: bool) {
+        let world = self.app.world_mut();
+        if world.resource::<RunPhase>().pipes_locked() {
+            return;
+        }
+        let mut pipes = world.query::<&mut InputPipe>();
+        for mut pipe in pipes.iter_mut(world) {
+            pipe.enabled = enabled;
+        }
+    }
+
+    // same as pressing the run button in the build phase
+    pub fn start_run(&mut self) {
+        let _ = self.app.world_mut().run_system_once(
+            |mut phase: ResMut<RunPhase>, wallet: Wallet, mut pipes: Query<&mut InputPipe>| {
+                if *phase == RunPhase::Building {
+                    start_run(&mut phase, &wallet, &mut pipes);
+                }
+            },
+        );
+    }
+
+    // same as pressing the reset run button, the layout stays
+    pub fn reset_run(&mut self) {
+        let _ = self.app.world_mut().run_system_once(
+            |commands: Commands,
+             mut phase: ResMut<RunPhase>,
+             mut wallet: Wallet,
+             mut stats: ResMut<SimulationStats>,
+             mut income: ResMut<IncomeMeter>,
+             mut rng: ResMut<SimulationRng>,
+             items: Query<Entity, (With<Item>, With<Velocity>)>,
+             mut pipes: Query<&mut InputPipe>,
+             mut output_pipes: Query<&mut OutputPipe>,
+             mut machines: Query<(&mut ProcessingQueue, Option<&mut IngredientBuffer>)>| {
+                reset_run(
+                    commands,
+                    &mut phase,
+                    &mut wallet,
+                    &mut stats,
+                    &mut income,
+                    &mut rng,
+                    &items,
+                    &mut pipes,
+                    &mut output_pipes,
+                    &mut machines,
+                );
+            },
+        );
+    }
+
+    // clicks the machine listed at `shop_slot` in the level file in the shop, which starts placing it.
+    // false if it can't be afforded, is sold out or the shop is closed
+    pub fn pick_machine(&mut self, shop_slot: usize) -> bool {
+        let world = self.app.world();
+        let current_level = world.resource::<CurrentLevel>().0;
+        let Some(machine) = world
+            .resource::<LevelRegistry>()
+            .0
+            .get(&current_level)
+            .and_then(|level| level.machine_shop.get(shop_slot).copied())
+        else {
+            return false;
+        };
+        let entry = ShopEntry {
+            slot: shop_slot,
+            machine,
+        };
+
+        self.app
+            .world_mut()
+            .run_system_once(
+                move |commands: Commands,
+                      wallet: Wallet,
+                      stock: Res<ShopStock>,
+                      phase: Res<RunPhase>,
+                      mut placement: ResMut<MachinePlacement>,
+                      ghosts: Query<Entity, With<PlacementGhost>>| {
+                    if !entry.can_buy(wallet.balance(), &stock, &phase) {
+                        return false;
+                    }
+                    start_placement(
+                        commands,
+                        &mut placement,
+                        &ghosts,
+                        entry,
+                        Position(Vec2::ZERO),
+                    );
+                    true
+                },
+            )
+            .unwrap_or(false)
+    }
+
+    // a left click at `position` while placing a machine, false if nothing was placed
+    pub fn click_placement(&mut self, position: Vec2) -> bool {
+        let world = self.app.world_mut();
+        world.resource_mut::<MouseWorldPosition>().0 = Some(Position(position));
+        world
+            .resource_mut::<ButtonInput<MouseButton>>()
+            .press(MouseButton::Left);
+        let _ = world.run_system_once(place_machine);
+        let mut buttons = world.resource_mut::<ButtonInput<MouseButton>>();
+        buttons.release(MouseButton::Left);
+        buttons.clear();
+        world.resource::<MachinePlacement>().0.is_none()
+    }
+
+    // presses `key` for a frame and lets go of it in the next one
+    pub fn press_key(&mut self, key_code: KeyCode) {
+        for state in [ButtonState::Pressed, ButtonState::Released] {
+            self.app.world_mut().send_event(KeyboardInput {
+                key_code,
+                logical_key: Key::Unidentified(NativeKey::Unidentified),
+                state,
+                repeat: false,
+                window: Entity::PLACEHOLDER,
+            });
+            self.step(1);
+        }
+    }
+
+    // picks the machine in the shop and places it at `position` right away.
+    // false if it can't be bought or the spot is blocked, the machine isn't held on to then
+    pub fn buy_machine(&mut self, shop_slot: usize, position: Vec2) -> bool {
+        if !self.pick_machine(shop_slot) {
+            return false;
+        }
+        if self.click_placement(position) {
+            return true;
+        }
+        let world = self.app.world_mut();
+        world
+            .resource_mut::<ButtonInput<KeyCode>>()
+            .press(KeyCode::Escape);
+        let _ = world.run_system_once(cancel_placement);
+        let mut keys = world.resource_mut::<ButtonInput<KeyCode>>();
+        keys.release(KeyCode::Escape);
+        keys.clear();
+        false
+    }
+
+    // sells the machine at once, as confirming the tooltip would. false if `entity` isn't a machine
+    pub fn sell_machine(&mut self, entity: Entity) -> bool {
+        self.app
+            .world_mut()
+            .run_system_once(
+                move |commands: Commands,
+                      machines: Query<(&Machine, Option<&ShopSlot>)>,
+                      followers: Query<(Entity, &FollowWithOffset)>,
+                      mut money_changes: EventWriter<MoneyChanged>,
+                      mut stock: ResMut<ShopStock>,
+                      refund_percent: Res<RefundPercent>,
+                      phase: Res<RunPhase>| {
+                    let Ok((machine, slot)) = machines.get(entity) else {
+                        return false;
+                    };
+                    if !phase.shop_open() {
+                        return false;
+                    }
+                    sell_machine(
+                        commands,
+                        &mut money_changes,
+                        &mut stock,
+                        refund_percent.0,
+                        entity,
+                        machine,
+                        slot,
+                        &followers,
+                    );
+                    true
+                },
+            )
+            .unwrap_or(false)
+    }
+
+    // draws a straight stroke as one mouse movement would, stops early when the level's ink runs out
+    pub fn draw_line(&mut self, from: Vec2, to: Vec2) {
+        let _ = self.app.world_mut().run_system_once(
+            move |commands: Commands,
+                  mut meshes: ResMut<Assets<Mesh>>,
+                  mut materials: ResMut<Assets<ColorMaterial>>,
+                  mut history: ResMut<EditHistory>,
+                  ink_budget: Res<InkBudget>,
+                  drawn: Query<&DrawnObstacle>| {
+                let ink = ink_left(&ink_budget, ink_used(drawn.iter())).unwrap_or(u32::MAX);
+                if ink == 0 {
+                    return;
+                }
+                let mut points = vec![from];
+                extend_stroke(&mut points, to, ink as usize - 1);
+                let stroke = Drawing::Stroke(points);
+                spawn_drawing(commands, &mut meshes, &mut materials, stroke.clone());
+                history.record_drawn(vec![stroke]);
+            },
+        );
+    }
+
+    // one freehand stroke with the draw tool, the mouse moving from `from` to `to` over a few frames
+    pub fn draw_freehand(&mut self, from: Vec2, to: Vec2) {
+        const MOUSE_STEP: f32 = 20.0; // distance the mouse moves per frame
+        let world = self.app.world_mut();
+        // registered for the whole stroke, `draw_obstacle` keeps the active stroke in a `Local`
+        let draw = world.register_system(draw_obstacle);
+        let tool = world.resource::<SelectedTool>().0;
+        world.resource_mut::<SelectedTool>().0 = Tools::Draw;
+        world
+            .resource_mut::<ButtonInput<MouseButton>>()
+            .press(MouseButton::Left);
+        let moves = (from.distance(to) / MOUSE_STEP).ceil().max(1.0) as usize;
+        for step in 0..=moves {
+            let position = from.lerp(to, step as f32 / moves as f32);
+            world.resource_mut::<MouseWorldPosition>().0 = Some(Position(position));
+            let _ = world.run_system(draw);
+        }
+        world
+            .resource_mut::<ButtonInput<MouseButton>>()
+            .release(MouseButton::Left);
+        let _ = world.run_system(draw);
+        let _ = world.run_system_once(finish_strokes);
+        world.resource_mut::<ButtonInput<MouseButton>>().clear();
+        world.resource_mut::<SelectedTool>().0 = tool;
+        let _ = world.unregister_system(draw);
+    }
+
+    // one click-drag with a shape tool, false if the shape was refused
+    pub fn place_shape(&mut self, tool: Tools, start: Vec2, end: Vec2) -> bool {
+        self.app
+            .world_mut()
+            .run_system_once(
+                move |commands: Commands,
+                      mut meshes: ResMut<Assets<Mesh>>,
+                      mut materials: ResMut<Assets<ColorMaterial>>,
+                      mut history: ResMut<EditHistory>,
+                      ink_budget: Res<InkBudget>,
+                      drawn: Query<&DrawnObstacle>| {
+                    let ink = ink_left(&ink_budget, ink_used(drawn.iter()));
+                    place_shape(
+                        commands,
+                        &mut meshes,
+                        &mut materials,
+                        &mut history,
+                        ink,
+                        Shape { tool, start, end },
+                    )
+                },
+            )
+            .unwrap_or(false)
+    }
+
+    // one eraser stroke from `from` to `to`
+    pub fn erase_line(&mut self, from: Vec2, to: Vec2) {
+        let mut brushes = vec![from];
+        extend_stroke(&mut brushes, to, usize::MAX);
+        for brush in brushes {
+            let _ = self.app.world_mut().run_system_once(
+                move |commands: Commands,
+                      mut meshes: ResMut<Assets<Mesh>>,
+                      mut materials: ResMut<Assets<ColorMaterial>>,
+                      drawn: Query<(Entity, &DrawnObstacle)>,
+                      mut history: ResMut<EditHistory>| {
+                    erase_at(
+                        commands,
+                        &mut meshes,
+                        &mut materials,
+                        &drawn,
+                        brush,
+                        &mut history,
+                    );
+                },
+            );
+        }
+        self.app
+            .world_mut()
+            .resource_mut::<EditHistory>()
+            .finish_stroke();
+    }
+
+    // same as pressing Ctrl+Z
+    pub fn undo(&mut self) {
+        self.apply_history(EditHistory::undo);
+    }
+
+    // same as pressing Ctrl+Y
+    pub fn redo(&mut self) {
+        self.apply_history(EditHistory::redo);
+    }
+
+    fn apply_history(&mut self, take: fn(&mut EditHistory, Option<u32>) -> Option<Edit>) {
+        let _ = self.app.world_mut().run_system_once(
+            move |commands: Commands,
+                  mut history: ResMut<EditHistory>,
+                  mut meshes: ResMut<Assets<Mesh>>,
+                  mut materials: ResMut<Assets<ColorMaterial>>,
+                  mut positions: Query<(Entity, &mut Position, Option<&DrawnObstacle>)>,
+                  mut rotations: Query<&mut Rotation>,
+                  ink_budget: Res<InkBudget>| {
+                let ink = ink_left_in(&ink_budget, &positions);
+                if let Some(edit) = take(&mut history, ink) {
+                    apply_edit(
+                        commands,
+                        &mut meshes,
+                        &mut materials,
+                        &mut positions,
+                        &mut rotations,
+                        edit,
+                    );
+                }
+            },
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use crate::game::*;
+
+// The speeds offered by the buttons. 0 stops the clock but leaves the level open for editing,
+// the escape menu (`LevelState::Paused`) stops the clock the same way and covers the level.
+pub const TIME_SCALES: [f32; 4] = [0.0, 1.0, 2.0, 4.0];
+
+const BUTTON_COLOR: Color = Color::srgb(0.25, 0.25, 0.3);
+const BUTTON_HOVER_COLOR: Color = Color::srgb(0.4, 0.4, 0.45);
+const SELECTED_BUTTON_COLOR: Color = Color::srgb(0.55, 0.45, 0.1);
+
+// How fast the simulation runs, applied to `Time<Virtual>`.
+// The fixed timestep stays the same, a faster speed runs more ticks per frame,
+// so a layout behaves the same at every speed.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct TimeScale(pub f32);
+
+impl Default for TimeScale {
+    fn default() -> Self {
+        TimeScale(1.0)
+    }
+}
+
+#[derive(Component)]
+pub struct TimeScaleButton(pub f32);
+
+pub fn apply_time_scale(scale: Res<TimeScale>, mut time: ResMut<Time<Virtual>>) {
+    if scale.is_changed() {
+        time.set_relative_speed(scale.0);
+    }
+}
+
+// every level starts at normal speed
+pub fn reset_time_scale(mut scale: ResMut<TimeScale>) {
+    *scale = TimeScale::default();
+}
+
+// the speed to go back to once the escape menu is closed
+#[derive(Resource, Default)]
+pub struct ScaleBeforePause(pub f32);
+
+pub fn pause_time_scale(mut scale: ResMut<TimeScale>, mut before: ResMut<ScaleBeforePause>) {
+    before.0 = scale.0;
+    scale.0 = 0.0;
+}
+
+pub fn resume_time_scale(mut scale: ResMut<TimeScale>, before: Res<ScaleBeforePause>) {
+    scale.0 = before.0;
+}
+
+// space pauses and resumes, 1, 2 and 4 pick the speed
+pub fn time_scale_keys(keys: Res<ButtonInput<KeyCode>>, mut scale: ResMut<TimeScale>) {
+    if keys.just_pressed(KeyCode::Space) {
+        scale.0 = if scale.0 == 0.0 { 1.0 } else { 0.0 };
+    } else if keys.just_pressed(KeyCode::Digit1) {
+        scale.0 = 1.0;
+    } else if keys.just_pressed(KeyCode::Digit2) {
+        scale.0 = 2.0;
+    } else if keys.just_pressed(KeyCode::Digit4) {
+        scale.0 = 4.0;
+    }
+}
+
+pub fn time_scale_buttons(
+    mut buttons: Query<(&Interaction, &TimeScaleButton, &mut BackgroundColor)>,
+    mut scale: ResMut<TimeScale>,
+) {
+    for (interaction, button, _) in buttons.iter() {
+        if *interaction == Interaction::Pressed {
+            scale.0 = button.0;
+        }
+    }
+    for (interaction, button, mut color) in buttons.iter_mut() {
+        *color = BackgroundColor(if button.0 == scale.0 {
+            SELECTED_BUTTON_COLOR
+        } else if *interaction == Interaction::Hovered {
+            BUTTON_HOVER_COLOR
+        } else {
+            BUTTON_COLOR
+        });
+    }
+}
+
+// a row of speed buttons at the top of the screen
+pub fn spawn_time_scale_buttons(mut commands: Commands, asset_server: &Res<AssetServer>) {
+    let font = asset_server.load("Fonts/CyberpunkCraftpixPixel.otf");
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(10.0),
+                left: Val::Px(700.0),
+                column_gap: Val::Px(8.0),
+                ..default()
+            },
+            BlocksWorldInput,
+            LevelEntity,
+        ))
+        .with_children(|row| {
+            for scale in TIME_SCALES {
+                let label = if scale == 0.0 {
+                    "||".to_string()
+                } else {
+                    format!("{}x", scale)
+                };
+                row.spawn((
+                    Button,
+                    TimeScaleButton(scale),
+                    Node {
+                        width: Val::Px(44.0),
+                        height: Val::Px(36.0),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    BackgroundColor(BUTTON_COLOR),
+                ))
+                .with_child((
+                    Text::new(label),
+                    TextFont {
+                        font: font.clone(),
+                        font_size: 20.,
+                        ..default()
+                    },
+                    TextColor(Color::srgb(211.0 / 255.0, 211.0 / 255.0, 211.0 / 255.0)),
+                ));
+            }
+        });
+}
@@ -3,7 +3,6 @@ use std::thread::spawn;
 use bevy::color::palettes::basic::*;
 use bevy::sprite::Anchor;
 use bevy::transform;
-use rand::Rng;
 
 use crate::game::*;
 use bevy::winit::cursor::CursorIcon;
@@ -14,15 +13,10 @@ use bevy::{
 
 pub const BUTTON_MOUSE_POS: Vec2 = Vec2::new(-740., 200.);
 pub const BUTTON_DRAW_POS: Vec2 = Vec2::new(-740., 90.);
-
-pub const MACHINE_BUTTON_1: Vec2 = Vec2::new(640.0, 256.0);
-pub const MACHINE_BUTTON_2: Vec2 = Vec2::new(640.0, 118.0);
-pub const MACHINE_BUTTON_3: Vec2 = Vec2::new(640.0, 108.0);
-pub const MACHINE_BUTTON_4: Vec2 = Vec2::new(640.0, 34.0);
-pub const MACHINE_BUTTON_5: Vec2 = Vec2::new(640.0, -40.0);
-
-#[derive(Component)]
-pub struct MachineWindow(Machine);
+pub const BUTTON_ERASE_POS: Vec2 = Vec2::new(-740., -20.);
+pub const BUTTON_LINE_POS: Vec2 = Vec2::new(-740., -130.);
+pub const BUTTON_RECTANGLE_POS: Vec2 = Vec2::new(-740., -240.);
+pub const BUTTON_FUNNEL_POS: Vec2 = Vec2::new(-740., -350.);
 
 #[derive(Resource)]
 pub struct SelectedTool(pub Tools);
@@ -31,6 +25,10 @@ pub struct SelectedTool(pub Tools);
 pub enum Tools {
     Mouse,
     Draw,
+    Erase,
+    Line,
+    Rectangle,
+    Funnel,
 }
 
 #[derive(Component)]
@@ -45,6 +43,16 @@ pub struct MoneyText;
 #[derive(Component)]
 pub struct GoalText;
 
+#[derive(Component)]
+pub struct InkText;
+
+#[derive(Component)]
+pub struct SellTooltip;
+
+// machine the player right-clicked once, right-clicking it again sells it
+#[derive(Resource, Default)]
+pub struct SellConfirmation(pub Option<Entity>);
+
 pub fn setup_ui(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
@@ -108,142 +116,265 @@ pub fn setup_ui(
         GoalText,
     ));
 
-    // spawn selection indicator
-    let mut sprite = Sprite::from_image(asset_server.load(SpriteView::ArrowLeft.get_sprite()));
-    sprite.custom_size = Some(SpriteView::ArrowLeft.get_scale());
-    commands.spawn((
-        SelectionIndicator,
-        Transform::from_translation(Vec3 {
-            x: 10000.,
-            y: 10000.,
-            z: -1000.,
-        }),
-        sprite,
-    ));
-
-    // spawn draw indicator
-    commands.spawn((
-        DrawIndicator,
-        Mesh2d(meshes.add(Circle::default())),
-        MeshMaterial2d(materials.add(Color::from(BLACK))),
-        Transform::from_xyz(10000., 10000., 1.).with_scale(Vec3::splat(24.)),
-    ));
-}
-
-pub fn buy_machines(
-    machine_windows: Query<(&MachineWindow, Entity)>,
-    clicked: Res<JustClicked>,
-    mut money: ResMut<CurrentMoney>,
-    mut commands: Commands,
-) {
-    for (window, window_entity) in machine_windows.iter() {
-        if let Some(clicked_entity) = clicked.0 {
-            if (clicked_entity == window_entity) {
-                if (money.0 > window.0.get_cost()) {
-                    // buy machine!
-                    money.0 -= window.0.get_cost();
-                    let mut rng = rand::rng();
-                    let random_spawn_x = rng.random_range(-200.0..200.0);
-                    let random_spawn_y = rng.random_range(-100.0..100.0);
-                    match window.0 {
-                        Machine::OneToOneCrafter {
-                            input,
-                            output,
-                            cost,
-                        } => {
-                            spawn_one_to_one_crafter(
-                                commands.reborrow(),
-                                Position(Vec2 {
-                                    x: random_spawn_x,
-                                    y: random_spawn_y,
-                                }),
-                                window.0,
-                            );
-                        }
-                        Machine::Duplicator { cost } => {
-                            spawn_duplicator(
-                                commands.reborrow(),
-                                Position(Vec2 {
-                                    x: random_spawn_x,
-                                    y: random_spawn_y,
-                                }),
-                                window.0,
-                            );
-                        }
-                    }
-                }
-            }
-        }
-    }
-}
-
-pub fn spawn_machine_window(
-    position: Position,
-    machine: Machine,
-    mut commands: Commands,
-    asset_server: &Res<AssetServer>,
-) {
     commands.spawn((
-        position,
-        SpriteView::MachineWindow,
-        MachineWindow(machine),
-        Clickable,
-    ));
-    commands.spawn((
-        Text::new(format!("{}", machine.get_cost())),
+        Text::new(""),
         TextFont {
             font: asset_server.load("Fonts/CyberpunkCraftpixPixel.otf"),
-            font_size: 40.,
+            font_size: 20.,
             ..default()
         },
-        TextColor(Color::srgb(255.0 / 255.0, 130.0 / 255.0, 130.0 / 255.0)),
+        TextColor(Color::srgb(211.0 / 255.0, 211.0 / 255.0, 211.0 / 255.0)),
         Node {
             position_type: PositionType::Absolute,
-            bottom: Val::Px(position.0.y + 450.0),
-            left: Val::Px(position.0.x + 800.0 - 70.0),
+            top: Val::Px(135.0),
+            left: Val::Px(30.0),
             ..default()
         },
-        Position(Vec2 { x: 0.0, y: 0.0 }),
+        InkText,
     ));
+
+    // money per second, averaged over the last few seconds
     commands.spawn((
-        Text::new(machine.get_name()),
+        Text::new(""),
         TextFont {
             font: asset_server.load("Fonts/CyberpunkCraftpixPixel.otf"),
-            font_size: 20.,
+            font_size: 16.,
             ..default()
         },
-        TextColor(Color::srgb(211.0 / 255.0, 211.0 / 255.0, 211.0 / 255.0)),
+        TextColor(Color::srgb(1.0, 215.0 / 255.0, 0.0)),
         Node {
             position_type: PositionType::Absolute,
-            bottom: Val::Px(position.0.y + 425.0),
-            left: Val::Px(position.0.x + 800.0 - 130.0),
+            top: Val::Px(160.0),
+            left: Val::Px(30.0),
             ..default()
         },
-        Position(Vec2 { x: 0.0, y: 0.0 }),
+        IncomeText,
     ));
+
     commands.spawn((
-        Text::new(machine.get_description()),
+        Text::new(""),
         TextFont {
             font: asset_server.load("Fonts/CyberpunkCraftpixPixel.otf"),
-            font_size: 12.,
+            font_size: 16.,
             ..default()
         },
-        TextColor(Color::srgb(211.0 / 255.0, 211.0 / 255.0, 211.0 / 255.0)),
+        TextColor(Color::srgb(1.0, 130.0 / 255.0, 130.0 / 255.0)),
         Node {
             position_type: PositionType::Absolute,
-            bottom: Val::Px(position.0.y + 410.0),
-            left: Val::Px(position.0.x + 800.0 - 130.0),
+            top: Val::Px(160.0),
+            left: Val::Px(160.0),
             ..default()
         },
-        Position(Vec2 { x: 0.0, y: 0.0 }),
+        ExpensesText,
     ));
-    let mut sprite = Sprite::from_image(asset_server.load(machine.get_sprite_view().get_sprite()));
-    sprite.custom_size = Some(Vec2::new(64., 64.));
+
+    // asks before a machine is sold, placed above it in the world
     commands.spawn((
+        Text2d::new(""),
+        TextFont {
+            font: asset_server.load("Fonts/CyberpunkCraftpixPixel.otf"),
+            font_size: 16.,
+            ..default()
+        },
+        TextColor(Color::srgb(1.0, 215.0 / 255.0, 0.0)),
+        Anchor::BottomCenter,
+        Transform::from_xyz(0., 0., 200.),
+        SellTooltip,
+    ));
+
+    // spawn selection indicator
+    let mut sprite = Sprite::from_image(asset_server.load(SpriteView::ArrowLeft.get_sprite()));
+    sprite.custom_size = Some(SpriteView::ArrowLeft.get_scale());
+    commands.spawn((
+        SelectionIndicator,
+        Transform::from_translation(Vec3 {
+            x: 10000.,
+            y: 10000.,
+            z: -1000.,
+        }),
         sprite,
-        Transform::from_xyz(position.0.x + 90., position.0.y, 200.0),
-        Position(Vec2 { x: 0.0, y: 0.0 }),
     ));
+
+    // spawn draw indicator
+    commands.spawn((
+        DrawIndicator,
+        Mesh2d(meshes.add(Circle::default())),
+        MeshMaterial2d(materials.add(Color::from(BLACK))),
+        Transform::from_xyz(10000., 10000., 1.).with_scale(Vec3::splat(24.)),
+    ));
+
+    // spawn erase indicator, a ring the size of the eraser
+    commands.spawn((
+        EraseIndicator,
+        Mesh2d(meshes.add(Annulus::new(ERASER_RADIUS - 2., ERASER_RADIUS))),
+        MeshMaterial2d(materials.add(Color::from(GRAY))),
+        Transform::from_xyz(10000., 10000., -1000.),
+    ));
+
+    // spawn shape preview, filled in while a shape is dragged out
+    commands.spawn((
+        ShapePreview,
+        Mesh2d(meshes.add(drawings_mesh(&[]))),
+        MeshMaterial2d(materials.add(Color::srgba(0., 0., 0., 0.4))),
+        Transform::from_xyz(0., 0., 2.),
+        Visibility::Hidden,
+    ));
+}
+
+// returns the new machine, None if the player can't afford it
+pub fn try_buy_machine(
+    commands: Commands,
+    wallet: &mut Wallet,
+    machine: Machine,
+    position: Position,
+) -> Option<Entity> {
+    if wallet.balance() <= machine.get_cost() {
+        return None;
+    }
+    // buy machine!
+    let entity = spawn_machine(commands, position, machine);
+    wallet.send(MoneyChanged {
+        amount: -machine.get_cost(),
+        reason: MoneyReason::MachineBought,
+        source_entity: Some(entity),
+    });
+    Some(entity)
+}
+
+// seconds a floating text stays up
+pub const FLOATING_TEXT_LIFETIME: f32 = 1.2;
+const FLOATING_TEXT_SPEED: f32 = 60.0; // pixels per second upwards
+
+// short feedback text in the world, rises and fades out
+#[derive(Component)]
+pub struct FloatingText {
+    pub age: f32,
+}
+
+pub fn spawn_floating_text(
+    mut commands: Commands,
+    asset_server: &AssetServer,
+    text: String,
+    color: Color,
+    position: Vec2,
+) {
+    commands.spawn((
+        FloatingText { age: 0.0 },
+        Text2d::new(text),
+        TextFont {
+            font: asset_server.load("Fonts/CyberpunkCraftpixPixel.otf"),
+            font_size: 24.,
+            ..default()
+        },
+        TextColor(color),
+        Transform::from_translation(position.extend(200.)),
+        LevelEntity,
+    ));
+}
+
+pub fn update_floating_texts(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut texts: Query<(Entity, &mut FloatingText, &mut Transform, &mut TextColor)>,
+) {
+    for (entity, mut floating, mut transform, mut color) in texts.iter_mut() {
+        floating.age += time.delta_secs();
+        if floating.age >= FLOATING_TEXT_LIFETIME {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        transform.translation.y += FLOATING_TEXT_SPEED * time.delta_secs();
+        color
+            .0
+            .set_alpha(1.0 - floating.age / FLOATING_TEXT_LIFETIME);
+    }
+}
+
+// removes the machine and pays back part of its cost
+#[allow(clippy::too_many_arguments)]
+pub fn sell_machine(
+    commands: Commands,
+    money_changes: &mut EventWriter<MoneyChanged>,
+    stock: &mut ShopStock,
+    refund_percent: u32,
+    entity: Entity,
+    machine: &Machine,
+    slot: Option<&ShopSlot>,
+    followers: &Query<(Entity, &FollowWithOffset)>,
+) {
+    money_changes.send(MoneyChanged {
+        amount: machine.get_refund(refund_percent),
+        reason: MoneyReason::MachineSold,
+        source_entity: Some(entity),
+    });
+    if let Some(slot) = slot {
+        stock.restock(slot.0);
+    }
+    despawn_with_followers(commands, entity, followers);
+}
+
+// right-clicking a machine asks for confirmation, moving off it or dragging cancels
+#[allow(clippy::too_many_arguments)]
+pub fn sell_machines(
+    commands: Commands,
+    buttons: Res<ButtonInput<MouseButton>>,
+    over_element: Res<OverClickableElement>,
+    dragging: Res<Dragging>,
+    machines: Query<(&Machine, Option<&ShopSlot>)>,
+    followers: Query<(Entity, &FollowWithOffset)>,
+    mut confirmation: ResMut<SellConfirmation>,
+    mut money_changes: EventWriter<MoneyChanged>,
+    mut stock: ResMut<ShopStock>,
+    refund_percent: Res<RefundPercent>,
+    phase: Res<RunPhase>,
+) {
+    if confirmation.0 != over_element.0 || dragging.entity.is_some() || !phase.shop_open() {
+        confirmation.0 = None;
+    }
+    if !buttons.just_pressed(MouseButton::Right) || dragging.entity.is_some() {
+        return;
+    }
+    let Some(entity) = over_element.0 else {
+        return;
+    };
+    let Ok((machine, slot)) = machines.get(entity) else {
+        return;
+    };
+    if confirmation.0 == Some(entity) {
+        confirmation.0 = None;
+        sell_machine(
+            commands,
+            &mut money_changes,
+            &mut stock,
+            refund_percent.0,
+            entity,
+            machine,
+            slot,
+            &followers,
+        );
+    } else {
+        confirmation.0 = Some(entity);
+    }
+}
+
+pub fn update_sell_tooltip(
+    confirmation: Res<SellConfirmation>,
+    machines: Query<(&Machine, &Position)>,
+    refund_percent: Res<RefundPercent>,
+    mut tooltip: Query<(&mut Text2d, &mut Transform), With<SellTooltip>>,
+) {
+    let (mut text, mut transform) = tooltip.single_mut();
+    let Some((machine, pos)) = confirmation.0.and_then(|entity| machines.get(entity).ok()) else {
+        text.0 = String::new();
+        return;
+    };
+    text.0 = format!(
+        "Sell {} for {}? Right-click again",
+        machine.get_name(),
+        machine.get_refund(refund_percent.0)
+    );
+    transform.translation.x = pos.0.x;
+    transform.translation.y = pos.0.y + machine.get_sprite_view().get_scale().y / 2.0;
 }
 
 pub fn update_level_text(
@@ -269,6 +400,29 @@ pub fn update_goal_text(
     goal_text.single_mut().0 = format!("Goal: {:?}", current_goal.0);
 }
 
+// hidden in levels without an ink limit, red while drawing is refused
+pub fn update_ink_text(
+    ink_budget: Res<InkBudget>,
+    drawn: Query<&DrawnObstacle>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    tool_selected: Res<SelectedTool>,
+    mut ink_text: Query<(&mut Text, &mut TextColor), With<InkText>>,
+) {
+    let (mut text, mut color) = ink_text.single_mut();
+    let Some(ink) = ink_left(&ink_budget, ink_used(drawn.iter())) else {
+        text.0 = String::new();
+        return;
+    };
+    let refused = ink == 0 && tool_selected.0 == Tools::Draw && buttons.pressed(MouseButton::Left);
+    if refused {
+        text.0 = "Out of ink!".to_string();
+        color.0 = Color::srgb(1.0, 130.0 / 255.0, 130.0 / 255.0);
+    } else {
+        text.0 = format!("Ink: {}", ink);
+        color.0 = Color::srgb(211.0 / 255.0, 211.0 / 255.0, 211.0 / 255.0);
+    }
+}
+
 pub fn update_mouse_pointer(
     over_element: Res<OverClickableElement>,
     dragging: Res<Dragging>,
@@ -335,6 +489,34 @@ pub fn tool_selection(
                         z: 101.,
                     })
                 }
+                Tools::Erase => {
+                    *transform = Transform::from_translation(Vec3 {
+                        x: BUTTON_ERASE_POS.x + 80.,
+                        y: BUTTON_ERASE_POS.y,
+                        z: 101.,
+                    })
+                }
+                Tools::Line => {
+                    *transform = Transform::from_translation(Vec3 {
+                        x: BUTTON_LINE_POS.x + 80.,
+                        y: BUTTON_LINE_POS.y,
+                        z: 101.,
+                    })
+                }
+                Tools::Rectangle => {
+                    *transform = Transform::from_translation(Vec3 {
+                        x: BUTTON_RECTANGLE_POS.x + 80.,
+                        y: BUTTON_RECTANGLE_POS.y,
+                        z: 101.,
+                    })
+                }
+                Tools::Funnel => {
+                    *transform = Transform::from_translation(Vec3 {
+                        x: BUTTON_FUNNEL_POS.x + 80.,
+                        y: BUTTON_FUNNEL_POS.y,
+                        z: 101.,
+                    })
+                }
             }
         }
     }
@@ -346,6 +528,7 @@ pub fn setup_mouse_button(mut commands: Commands) {
         Position(BUTTON_MOUSE_POS),
         Clickable,
         Tools::Mouse,
+        LevelEntity,
     ));
 }
 
@@ -355,5 +538,46 @@ pub fn setup_draw_button(mut commands: Commands) {
         Position(BUTTON_DRAW_POS),
         Clickable,
         Tools::Draw,
+        LevelEntity,
+    ));
+}
+
+pub fn setup_erase_button(mut commands: Commands) {
+    commands.spawn((
+        SpriteView::ButtonErase,
+        Position(BUTTON_ERASE_POS),
+        Clickable,
+        Tools::Erase,
+        LevelEntity,
+    ));
+}
+
+pub fn setup_line_button(mut commands: Commands) {
+    commands.spawn((
+        SpriteView::ButtonLine,
+        Position(BUTTON_LINE_POS),
+        Clickable,
+        Tools::Line,
+        LevelEntity,
+    ));
+}
+
+pub fn setup_rectangle_button(mut commands: Commands) {
+    commands.spawn((
+        SpriteView::ButtonRectangle,
+        Position(BUTTON_RECTANGLE_POS),
+        Clickable,
+        Tools::Rectangle,
+        LevelEntity,
+    ));
+}
+
+pub fn setup_funnel_button(mut commands: Commands) {
+    commands.spawn((
+        SpriteView::ButtonFunnel,
+        Position(BUTTON_FUNNEL_POS),
+        Clickable,
+        Tools::Funnel,
+        LevelEntity,
     ));
 }
@@ -14,10 +14,16 @@ pub enum SpriteView {
     BackgroundCity2,
     ButtonMouse,
     ButtonDraw,
+    ButtonErase,
+    ButtonLine,
+    ButtonRectangle,
+    ButtonFunnel,
     ArrowLeft,
     OneToOneCrafter,
+    TwoToOneCrafter,
     Duplicator,
-    MachineWindow,
+    Sorter,
+    Conveyor { direction: BeltDirection },
     Item { item: Item, ui_element: bool },
 }
 
@@ -34,10 +40,16 @@ impl SpriteView {
             SpriteView::CursorPointRight => "UI/cursor_point_right.png",
             SpriteView::ButtonMouse => "UI/button_mouse.png",
             SpriteView::ButtonDraw => "UI/button_draw.png",
+            SpriteView::ButtonErase => "UI/button_erase.png",
+            SpriteView::ButtonLine => "UI/button_line.png",
+            SpriteView::ButtonRectangle => "UI/button_rectangle.png",
+            SpriteView::ButtonFunnel => "UI/button_funnel.png",
             SpriteView::ArrowLeft => "UI/arrow_left.png",
             SpriteView::OneToOneCrafter => "Machines/1_1_crafter.png",
+            SpriteView::TwoToOneCrafter => "Machines/2_1_crafter.png",
             SpriteView::Duplicator => "Machines/duplicator.png",
-            SpriteView::MachineWindow => "UI/machine_window_empty.png",
+            SpriteView::Sorter => "Machines/sorter.png",
+            SpriteView::Conveyor { .. } => "Machines/conveyor.png",
             SpriteView::Item {
                 item,
                 ui_element: _,
@@ -57,10 +69,16 @@ impl SpriteView {
             SpriteView::BackgroundCity2 => "Background",
             SpriteView::ButtonMouse => "mouse",
             SpriteView::ButtonDraw => "draw",
+            SpriteView::ButtonErase => "erase",
+            SpriteView::ButtonLine => "line",
+            SpriteView::ButtonRectangle => "rectangle",
+            SpriteView::ButtonFunnel => "funnel",
             SpriteView::ArrowLeft => "arrow",
             SpriteView::OneToOneCrafter => "crafter",
+            SpriteView::TwoToOneCrafter => "crafter",
             SpriteView::Duplicator => "duplicator",
-            SpriteView::MachineWindow => "window",
+            SpriteView::Sorter => "sorter",
+            SpriteView::Conveyor { .. } => "conveyor",
             SpriteView::Item {
                 item,
                 ui_element: _,
@@ -80,10 +98,16 @@ impl SpriteView {
             SpriteView::BackgroundCity2 => Vec2::new(1600.0, 900.0),
             SpriteView::ButtonMouse => Vec2::new(100.0, 100.0),
             SpriteView::ButtonDraw => Vec2::new(100.0, 100.0),
+            SpriteView::ButtonErase => Vec2::new(100.0, 100.0),
+            SpriteView::ButtonLine => Vec2::new(100.0, 100.0),
+            SpriteView::ButtonRectangle => Vec2::new(100.0, 100.0),
+            SpriteView::ButtonFunnel => Vec2::new(100.0, 100.0),
             SpriteView::ArrowLeft => Vec2::new(70.0, 70.0),
             SpriteView::OneToOneCrafter => Vec2::new(200.0, 200.0),
+            SpriteView::TwoToOneCrafter => Vec2::new(400.0, 200.0),
             SpriteView::Duplicator => Vec2::new(400.0, 200.0),
-            SpriteView::MachineWindow => Vec2::new(320.0, 128.0),
+            SpriteView::Sorter => Vec2::new(200.0, 200.0),
+            SpriteView::Conveyor { .. } => Vec2::new(200.0, 24.0),
             SpriteView::Item {
                 item: _,
                 ui_element,
@@ -109,10 +133,16 @@ impl SpriteView {
             SpriteView::BackgroundCity2 => -10.,
             SpriteView::ButtonMouse => 100.,
             SpriteView::ButtonDraw => 100.,
+            SpriteView::ButtonErase => 100.,
+            SpriteView::ButtonLine => 100.,
+            SpriteView::ButtonRectangle => 100.,
+            SpriteView::ButtonFunnel => 100.,
             SpriteView::ArrowLeft => 100.,
             SpriteView::OneToOneCrafter => 9.,
+            SpriteView::TwoToOneCrafter => 9.2,
             SpriteView::Duplicator => 9.1,
-            SpriteView::MachineWindow => 100.,
+            SpriteView::Sorter => 9.3,
+            SpriteView::Conveyor { .. } => 9.4,
             SpriteView::Item {
                 item: _,
                 ui_element,
@@ -131,30 +161,49 @@ impl SpriteView {
             _ => Anchor::Center,
         }
     }
+
+    // the sprites face right
+    pub fn get_flip_x(&self) -> bool {
+        matches!(
+            self,
+            SpriteView::Conveyor {
+                direction: BeltDirection::Left
+            }
+        )
+    }
 }
 
 pub fn on_add_view(
     trigger: Trigger<OnAdd, SpriteView>,
     asset_server: Res<AssetServer>,
-    views: Query<(&SpriteView, &Position)>,
+    views: Query<(&SpriteView, &Position, Option<&Rotation>)>,
     mut commands: Commands,
 ) {
     let entity = trigger.entity();
-    if let Ok((view, pos)) = views.get(entity) {
+    if let Ok((view, pos, rotation)) = views.get(entity) {
         let mut sprite = Sprite::from_image(asset_server.load(view.get_sprite()));
         sprite.custom_size = Some(view.get_scale());
         sprite.anchor = view.get_anchor();
+        sprite.flip_x = view.get_flip_x();
         commands.entity(entity).insert((
             sprite,
-            Transform::from_xyz(pos.0.x, pos.0.y, view.get_layer()),
+            Transform::from_xyz(pos.0.x, pos.0.y, view.get_layer())
+                .with_rotation(Quat::from_rotation_z(rotation.map_or(0.0, |r| r.0))),
         ));
     }
 }
 
 /* remove items that fall out of the game space (for performance) */
-pub fn remove_escaped_items(mut commands: Commands, items: Query<(Entity, &Position)>) {
-    for (entity, pos) in items.iter() {
+pub fn remove_escaped_items(
+    mut commands: Commands,
+    items: Query<(Entity, &Position, Has<Item>)>,
+    mut stats: ResMut<SimulationStats>,
+) {
+    for (entity, pos, is_item) in items.iter() {
         if (pos.0.distance(Vec2 { x: 0., y: 0. }) > 1000.0) {
+            if is_item {
+                stats.items_escaped += 1;
+            }
             commands.entity(entity).despawn();
         }
     }
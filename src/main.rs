@@ -3,8 +3,7 @@ use bevy::audio::PlaybackSettings;
 use bevy::audio::Volume;
 use bevy::prelude::*; // Import PlaybackSettings
 
-mod game;
-use game::*;
+use assembly_anarchy::game::*;
 
 fn main() {
     App::new()
@@ -28,45 +27,28 @@ fn main() {
                 })
                 .set(ImagePlugin::default_nearest()),
         )
+        .add_plugins(SimulationPlugin::default())
+        .insert_resource(Progress::load())
         .insert_resource(MouseWorldPosition(None))
         .insert_resource(LeftMouseClickPosition(None))
         .insert_resource(JustClicked(None))
         .insert_resource(OverClickableElement(None))
-        .insert_resource(LevelRegistry::default())
-        .insert_resource(CurrentLevel(0))
-        .insert_resource(CurrentMoney(-1))
-        .insert_resource(MoneyGoal(0))
-        .insert_resource(LevelWon(false))
-        .insert_resource(LevelLost(false))
-        .insert_resource(SelectedTool(Tools::Mouse))
         .insert_resource(Dragging {
             entity: None,
             offset_pos: None,
         })
-        .add_observer(on_add_view)
-        .add_observer(on_add_output_pipe)
-        .add_observer(on_add_input_pipe)
-        .add_systems(
-            Startup,
-            (setup_camera, setup_music, setup_ui, load_initial_level),
-        )
-        .add_systems(
-            Update,
-            (
-                add_gravity,
-                collision_with_static_circles,
-                collision_with_static_rectangles,
-                update_position,
-                on_changed_position,
-            )
-                .chain(),
-        )
+        .add_systems(Startup, (setup_camera, setup_music, setup_ui))
         .add_systems(
             Update,
             (
                 update_level_text,
                 update_money_text,
                 update_goal_text,
+                update_ink_text,
+                update_sell_tooltip,
+                update_pipe_quality_texts,
+                update_income_texts,
+                spawn_money_popups,
                 update_mouse_pointer,
                 tool_selection,
             ),
@@ -75,26 +57,40 @@ fn main() {
             Update,
             (
                 toggle_input_pipe,
-                input_pipe_spawn_item,
-                output_pipe_consume_item,
                 draw_obstacle,
-                remove_escaped_items,
-                level_management,
-                one_to_one_crafter,
-                duplicator,
-                follow_relative_position,
-                buy_machines,
-            ),
+                erase_obstacles,
+                finish_strokes.after(draw_obstacle).after(erase_obstacles),
+                draw_shapes,
+                update_shape_preview.after(draw_shapes),
+                toggle_grid,
+                draw_grid,
+                buy_machines.after(apply_money_changes),
+                update_shop_entries.after(apply_money_changes),
+                scroll_shop,
+                time_scale_keys,
+                time_scale_buttons,
+                run_button.after(apply_money_changes),
+                place_machine.after(apply_money_changes).after(buy_machines),
+                update_placement_ghost.after(place_machine),
+                sell_machines.after(apply_money_changes),
+            )
+                .run_if(in_state(LevelState::Playing)),
         )
+        .add_systems(OnEnter(AppState::Menu), spawn_main_menu)
+        .add_systems(Update, level_buttons.run_if(in_state(AppState::Menu)))
+        .add_systems(OnEnter(LevelState::Won), record_progress)
         .add_systems(
             Update,
             (
                 update_mouse_world_position,
                 update_left_mouse_click_position,
                 update_just_clicked,
+                block_input_over_ui,
+                record_moves,
                 reset_dragging,
                 update_dragged_object,
                 set_dragging,
+                rotate_dragged_machine,
             )
                 .chain(),
         )
@@ -0,0 +1,166 @@
+use assembly_anarchy::game::*;
+use bevy::prelude::*;
+
+fn drawings(sim: &mut HeadlessSimulation) -> Vec<Drawing> {
+    let world = sim.app.world_mut();
+    world
+        .query::<&DrawnObstacle>()
+        .iter(world)
+        .map(|drawn| drawn.0.clone())
+        .collect()
+}
+
+#[test]
+fn an_eraser_stroke_is_undone_as_a_whole() {
+    let points = vec![Vec2::new(0., 0.), Vec2::new(8., 0.), Vec2::new(16., 0.)];
+    let line = Drawing::Stroke(points.clone());
+    let piece = Drawing::Stroke(points[..2].to_vec());
+    let mut history = EditHistory::default();
+    history.record_drawn(vec![line.clone()]);
+    // the second touch erases part of what the first one left
+    history.record_erased(line.clone(), vec![piece.clone()]);
+    history.record_erased(piece, vec![]);
+    history.finish_stroke();
+
+    assert_eq!(
+        history.undo(None),
+        Some(Edit::Drawings {
+            removed: vec![],
+            added: vec![line.clone()],
+        })
+    );
+    assert_eq!(
+        history.undo(None),
+        Some(Edit::Drawings {
+            removed: vec![line],
+            added: vec![],
+        })
+    );
+    assert_eq!(history.undo(None), None);
+}
+
+#[test]
+fn new_edits_drop_the_redo_stack() {
+    let mut history = EditHistory::default();
+    history.record_drawn(vec![Drawing::Stroke(vec![Vec2::ZERO])]);
+    history.undo(None);
+    history.record_drawn(vec![Drawing::Stroke(vec![Vec2::ONE])]);
+    assert_eq!(history.redo(None), None);
+}
+
+#[test]
+fn undo_and_redo_drawn_strokes() {
+    let mut sim = HeadlessSimulation::new(1);
+    sim.draw_line(Vec2::new(-100., 0.), Vec2::new(100., 0.));
+    sim.step(1);
+    let drawn = drawings(&mut sim);
+    assert_eq!(drawn.len(), 1);
+
+    sim.undo();
+    sim.step(1);
+    assert!(drawings(&mut sim).is_empty());
+
+    sim.redo();
+    sim.step(1);
+    assert_eq!(drawings(&mut sim), drawn);
+}
+
+#[test]
+fn erasing_splits_a_stroke() {
+    let mut sim = HeadlessSimulation::new(1);
+    sim.draw_line(Vec2::new(-100., 0.), Vec2::new(100., 0.));
+    sim.step(1);
+    let drawn = drawings(&mut sim);
+    sim.erase_line(Vec2::new(0., 50.), Vec2::new(0., -50.));
+    sim.step(1);
+
+    let pieces = drawings(&mut sim);
+    assert_eq!(pieces.len(), 2);
+    for piece in pieces {
+        let Drawing::Stroke(points) = piece else {
+            panic!("erasing a stroke left {piece:?}");
+        };
+        assert!(points.iter().all(|point| point.x.abs() >= ERASER_RADIUS));
+    }
+
+    sim.undo();
+    sim.step(1);
+    assert_eq!(drawings(&mut sim), drawn);
+}
+
+#[test]
+fn undo_moves_and_turns_a_machine_back() {
+    let mut sim = HeadlessSimulation::new(3);
+    assert!(sim.buy_machine(0, Vec2::new(0., 100.)));
+    sim.step(1);
+    let world = sim.app.world_mut();
+    let (machine, mut pos, mut rotation) = world
+        .query_filtered::<(Entity, &mut Position, &mut Rotation), With<Machine>>()
+        .single_mut(world);
+    pos.0 = Vec2::new(200., 100.);
+    rotation.0 = ROTATION_STEP;
+    world.resource_mut::<EditHistory>().record(Edit::Move {
+        entity: machine,
+        from: Vec2::new(0., 100.),
+        to: Vec2::new(200., 100.),
+        from_rotation: 0.,
+        to_rotation: ROTATION_STEP,
+    });
+
+    sim.undo();
+    let world = sim.app.world();
+    assert_eq!(
+        world.get::<Position>(machine).unwrap().0,
+        Vec2::new(0., 100.)
+    );
+    assert_eq!(world.get::<Rotation>(machine).unwrap().0, 0.);
+
+    sim.redo();
+    let world = sim.app.world();
+    assert_eq!(
+        world.get::<Position>(machine).unwrap().0,
+        Vec2::new(200., 100.)
+    );
+    assert_eq!(world.get::<Rotation>(machine).unwrap().0, ROTATION_STEP);
+}
+
+#[test]
+fn loading_a_level_clears_the_history() {
+    let mut sim = HeadlessSimulation::new(1);
+    sim.draw_line(Vec2::new(-100., 0.), Vec2::new(100., 0.));
+    sim.app
+        .world_mut()
+        .resource_mut::<NextState<LevelState>>()
+        .set(LevelState::Loading);
+    sim.step(2);
+    assert_eq!(
+        sim.app.world_mut().resource_mut::<EditHistory>().undo(None),
+        None
+    );
+}
+
+#[test]
+fn a_shape_is_undone_as_a_whole() {
+    let mut sim = HeadlessSimulation::new(1);
+    assert!(sim.place_shape(Tools::Funnel, Vec2::new(-200., 100.), Vec2::new(200., 0.)));
+    sim.step(1);
+    assert_eq!(drawings(&mut sim).len(), 2);
+
+    sim.undo();
+    sim.step(1);
+    assert!(drawings(&mut sim).is_empty());
+}
+
+#[test]
+fn erasing_removes_a_whole_rectangle() {
+    let mut sim = HeadlessSimulation::new(1);
+    assert!(sim.place_shape(
+        Tools::Rectangle,
+        Vec2::new(-100., -20.),
+        Vec2::new(100., 20.)
+    ));
+    sim.step(1);
+    sim.erase_line(Vec2::new(0., 60.), Vec2::new(0., 30.));
+    sim.step(1);
+    assert!(drawings(&mut sim).is_empty());
+}
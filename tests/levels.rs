@@ -0,0 +1,823 @@
+use assembly_anarchy::game::*;
+use bevy::ecs::system::RunSystemOnce;
+use bevy::prelude::*;
+
+// generous upper bound, every solution below finishes well within a simulated minute
+const MAX_TICKS: u32 = 60 * 120;
+
+fn draw(sim: &mut HeadlessSimulation, from: (f32, f32), to: (f32, f32)) {
+    sim.draw_line(Vec2::new(from.0, from.1), Vec2::new(to.0, to.1));
+}
+
+fn run_with_input_enabled(sim: &mut HeadlessSimulation) -> SimulationReport {
+    sim.set_input_pipes_enabled(true);
+    sim.run(MAX_TICKS)
+}
+
+// toggles the input pipes on and off, giving the products time to reach the output before buying more
+fn run_in_bursts(sim: &mut HeadlessSimulation, on_ticks: u32, off_ticks: u32) -> SimulationReport {
+    loop {
+        sim.set_input_pipes_enabled(true);
+        let report = sim.run(on_ticks);
+        if report.won || report.lost || report.ticks > MAX_TICKS {
+            return report;
+        }
+        sim.set_input_pipes_enabled(false);
+        let report = sim.run(off_ticks);
+        if report.won || report.lost {
+            return report;
+        }
+    }
+}
+
+fn assert_won(level: u32, report: SimulationReport) {
+    assert!(
+        report.won && !report.lost,
+        "level {} was not solved: {:?}",
+        level,
+        report
+    );
+}
+
+#[test]
+fn all_level_files_load() {
+    let sim = HeadlessSimulation::new(0);
+    let registry = sim.app.world().resource::<LevelRegistry>();
+    for level in 0..=9 {
+        assert!(registry.0.contains_key(&level), "level {} missing", level);
+    }
+}
+
+#[test]
+fn level_0_is_the_explainer() {
+    let mut sim = HeadlessSimulation::new(0);
+    let report = sim.run(1);
+    assert_won(0, report);
+    assert_eq!(report.items_spawned, 0);
+}
+
+#[test]
+fn level_1_is_solvable() {
+    // the output pipe is right below the input pipe
+    let mut sim = HeadlessSimulation::new(1);
+    let report = run_with_input_enabled(&mut sim);
+    assert_won(1, report);
+    assert!(report.items_consumed > 0);
+}
+
+#[test]
+fn level_2_is_solvable() {
+    let mut sim = HeadlessSimulation::new(2);
+    draw(&mut sim, (-80., 300.), (540., -150.));
+    draw(&mut sim, (660., 100.), (660., -300.));
+    assert_won(2, run_with_input_enabled(&mut sim));
+}
+
+#[test]
+fn level_3_is_solvable() {
+    let mut sim = HeadlessSimulation::new(3);
+    assert!(sim.buy_machine(0, Vec2::new(0., 100.)));
+    assert_won(3, run_with_input_enabled(&mut sim));
+}
+
+#[test]
+fn level_4_is_solvable() {
+    let mut sim = HeadlessSimulation::new(4);
+    assert!(sim.buy_machine(0, Vec2::new(300., 280.)));
+    draw(&mut sim, (360., 120.), (-450., -200.));
+    // funnel into the output pipe
+    draw(&mut sim, (-470., -230.), (-550., -340.));
+    draw(&mut sim, (-770., -100.), (-650., -340.));
+    assert_won(4, run_with_input_enabled(&mut sim));
+}
+
+#[test]
+fn level_5_is_solvable() {
+    let mut sim = HeadlessSimulation::new(5);
+    assert!(sim.buy_machine(0, Vec2::new(-300., 250.)));
+    draw(&mut sim, (-360., 120.), (-40., -200.));
+    draw(&mut sim, (-60., -230.), (-50., -340.));
+    draw(&mut sim, (160., -100.), (50., -340.));
+    sim.start_run();
+    assert_won(5, sim.run(MAX_TICKS));
+}
+
+#[test]
+fn level_6_is_solvable() {
+    // one duplicator can't keep up with the pipe, a ridge splits the items between two
+    let mut sim = HeadlessSimulation::new(6);
+    assert!(sim.buy_machine(0, Vec2::new(-200., 150.)));
+    assert!(sim.buy_machine(0, Vec2::new(200., 150.)));
+    draw(&mut sim, (0., 345.), (-90., 270.));
+    draw(&mut sim, (0., 345.), (90., 270.));
+    draw(&mut sim, (-360., 30.), (-40., -300.));
+    draw(&mut sim, (360., 30.), (40., -300.));
+    assert_eq!(ink_spent(&mut sim), 146);
+    assert_won(6, run_with_input_enabled(&mut sim));
+}
+
+#[test]
+fn level_7_is_solvable() {
+    // three duplicators turn every item into four. the first one is the bottleneck,
+    // short bursts give it time to work through the items before the next ones
+    let mut sim = HeadlessSimulation::new(7);
+    assert!(sim.buy_machine(0, Vec2::new(0., 250.)));
+    assert!(sim.buy_machine(0, Vec2::new(-100., -50.)));
+    // there is no room for both below the first one, the right copies get steered aside
+    assert!(sim.buy_machine(0, Vec2::new(300., -40.)));
+    draw(&mut sim, (50., 140.), (220., 70.));
+    draw(&mut sim, (-320., -160.), (-45., -330.));
+    draw(&mut sim, (450., -140.), (45., -330.));
+    assert_won(7, run_in_bursts(&mut sim, 20, 120));
+}
+
+#[test]
+fn level_8_is_solvable() {
+    // two duplicators side by side behind a ridge, a crafter below each of their outputs
+    let mut sim = HeadlessSimulation::new(8);
+    assert!(sim.buy_machine(0, Vec2::new(-210., 180.)));
+    assert!(sim.buy_machine(0, Vec2::new(210., 180.)));
+    for x in [-310., -110., 110., 310.] {
+        assert!(sim.buy_machine(1, Vec2::new(x, -20.)));
+    }
+    draw(&mut sim, (0., 345.), (-100., 260.));
+    draw(&mut sim, (0., 345.), (100., 260.));
+    draw(&mut sim, (-430., -120.), (-40., -300.));
+    draw(&mut sim, (430., -120.), (40., -300.));
+    sim.start_run();
+    assert_won(8, sim.run(MAX_TICKS));
+}
+
+#[test]
+fn level_9_is_solvable() {
+    let mut sim = HeadlessSimulation::new(9);
+    assert_won(9, run_with_input_enabled(&mut sim));
+}
+
+#[test]
+fn running_out_of_money_loses_the_level() {
+    // nothing routes the bolts to the output pipe, so the player goes bankrupt
+    let mut sim = HeadlessSimulation::new(2);
+    let report = run_with_input_enabled(&mut sim);
+    assert!(report.lost && !report.won, "{:?}", report);
+    assert!(report.money < 0);
+    assert_eq!(report.items_consumed, 0);
+    assert!(report.items_escaped > 0);
+}
+
+fn item_positions(sim: &mut HeadlessSimulation) -> Vec<(Item, Vec2)> {
+    let world = sim.app.world_mut();
+    let mut items = world.query::<(&Item, &Position)>();
+    items
+        .iter(world)
+        .map(|(item, pos)| (*item, pos.0))
+        .collect()
+}
+
+#[test]
+fn same_seed_replays_bit_for_bit() {
+    let replay = |seed: u64| {
+        let plugin = SimulationPlugin {
+            seed,
+            ..Default::default()
+        };
+        let mut sim = HeadlessSimulation::with_plugin(6, plugin);
+        assert!(sim.buy_machine(0, Vec2::new(0., 150.)));
+        sim.set_input_pipes_enabled(true);
+        sim.step(300);
+        (sim.report(), item_positions(&mut sim))
+    };
+
+    let first = replay(7);
+    assert!(!first.1.is_empty());
+    assert_eq!(first, replay(7));
+    assert_ne!(first.1, replay(8).1);
+}
+
+#[test]
+fn level_1_is_solvable_at_a_higher_tick_rate() {
+    let plugin = SimulationPlugin {
+        tick_rate: 120.0,
+        ..Default::default()
+    };
+    let mut sim = HeadlessSimulation::with_plugin(1, plugin);
+    assert_won(1, run_with_input_enabled(&mut sim));
+}
+
+#[test]
+fn level_files_can_pre_place_conveyors() {
+    let source = r#"(
+        starting_money: 100,
+        money_goal: 200,
+        background: City,
+        conveyors: [
+            (x: -200.0, y: 0.0, direction: Right, speed: 100.0),
+        ],
+    )"#;
+    let level = LevelDefinition::parse("level_test.ron".into(), source).unwrap();
+    assert_eq!(level.conveyors.len(), 1);
+    assert_eq!(level.conveyors[0].direction, BeltDirection::Right);
+
+    let backwards = source.replace("speed: 100.0", "speed: -100.0");
+    assert!(LevelDefinition::parse("level_test.ron".into(), &backwards).is_err());
+}
+
+fn set_level_state(sim: &mut HeadlessSimulation, state: LevelState) {
+    let world = sim.app.world_mut();
+    world.resource_mut::<NextState<LevelState>>().set(state);
+}
+
+#[test]
+fn loading_state_replaces_the_current_level() {
+    // e.g. picked in the level select menu
+    let mut sim = HeadlessSimulation::new(1);
+    sim.app.world_mut().resource_mut::<CurrentLevel>().0 = 8;
+    set_level_state(&mut sim, LevelState::Loading);
+    sim.step(2);
+    let world = sim.app.world();
+    assert_eq!(world.resource::<CurrentMoney>().0, 3000);
+    assert_eq!(
+        *world.resource::<State<LevelState>>().get(),
+        LevelState::Playing
+    );
+}
+
+#[test]
+fn paused_level_stands_still() {
+    let mut sim = HeadlessSimulation::new(1);
+    sim.set_input_pipes_enabled(true);
+    sim.step(30);
+    set_level_state(&mut sim, LevelState::Paused);
+    sim.step(1);
+
+    let items = item_positions(&mut sim);
+    let money = sim.report().money;
+    assert!(!items.is_empty());
+    sim.step(60);
+    assert_eq!(item_positions(&mut sim), items);
+    assert_eq!(sim.report().money, money);
+}
+
+fn count_entities<F: bevy::ecs::query::QueryFilter>(sim: &mut HeadlessSimulation) -> usize {
+    let world = sim.app.world_mut();
+    world.query_filtered::<Entity, F>().iter(world).count()
+}
+
+#[test]
+fn reloading_despawns_exactly_the_level_entities() {
+    let mut sim = HeadlessSimulation::new(3);
+    let fresh = count_entities::<With<LevelEntity>>(&mut sim);
+    // not part of the level, even though it has a position
+    let bystander = sim.app.world_mut().spawn(Position(Vec2::ZERO)).id();
+
+    assert!(sim.buy_machine(0, Vec2::new(0., 100.)));
+    draw(&mut sim, (-300., 100.), (-100., 0.));
+    sim.set_input_pipes_enabled(true);
+    sim.step(60);
+    assert!(count_entities::<With<LevelEntity>>(&mut sim) > fresh);
+
+    set_level_state(&mut sim, LevelState::Loading);
+    sim.step(2);
+    assert_eq!(count_entities::<With<LevelEntity>>(&mut sim), fresh);
+    assert_eq!(count_entities::<With<Item>>(&mut sim), 0);
+    assert!(sim.app.world().get_entity(bystander).is_ok());
+}
+
+fn app_state(sim: &HeadlessSimulation) -> AppState {
+    *sim.app.world().resource::<State<AppState>>().get()
+}
+
+#[test]
+fn going_back_to_the_menu_despawns_the_level() {
+    let mut sim = HeadlessSimulation::new(3);
+    sim.set_input_pipes_enabled(true);
+    sim.step(60);
+    let world = sim.app.world_mut();
+    world
+        .resource_mut::<NextState<AppState>>()
+        .set(AppState::Menu);
+    sim.step(1);
+    assert_eq!(app_state(&sim), AppState::Menu);
+    assert_eq!(count_entities::<With<LevelEntity>>(&mut sim), 0);
+}
+
+#[test]
+fn missing_level_goes_back_to_the_menu() {
+    let mut sim = HeadlessSimulation::new(1);
+    sim.app.world_mut().resource_mut::<CurrentLevel>().0 = 99;
+    set_level_state(&mut sim, LevelState::Loading);
+    sim.step(2);
+    assert_eq!(app_state(&sim), AppState::Menu);
+    assert_eq!(count_entities::<With<LevelEntity>>(&mut sim), 0);
+}
+
+fn ink_spent(sim: &mut HeadlessSimulation) -> u32 {
+    let world = sim.app.world_mut();
+    ink_used(world.query::<&DrawnObstacle>().iter(world))
+}
+
+#[test]
+fn drawing_stops_when_the_ink_runs_out() {
+    let mut sim = HeadlessSimulation::new(6);
+    assert_eq!(sim.app.world().resource::<InkBudget>().0, Some(150));
+    // 1600 units long, far more than the ink allows
+    draw(&mut sim, (-800., 0.), (800., 0.));
+    draw(&mut sim, (-800., 100.), (800., 100.));
+    sim.step(1);
+    assert_eq!(ink_spent(&mut sim), 150);
+    assert_eq!(count_entities::<With<DrawnObstacle>>(&mut sim), 1);
+
+    // the second stroke was refused, undoing the first one gives all the ink back
+    sim.undo();
+    sim.step(1);
+    assert_eq!(ink_spent(&mut sim), 0);
+    draw(&mut sim, (-800., 100.), (800., 100.));
+    sim.step(1);
+    assert_eq!(ink_spent(&mut sim), 150);
+
+    // redoing the first stroke would need more ink than is left
+    sim.redo();
+    sim.step(1);
+    assert_eq!(ink_spent(&mut sim), 150);
+}
+
+#[test]
+fn freehand_drawing_counts_the_ink_of_shapes() {
+    let mut sim = HeadlessSimulation::new(6);
+    // 100 by 20, a 240 long outline
+    assert!(sim.place_shape(
+        Tools::Rectangle,
+        Vec2::new(-300., 200.),
+        Vec2::new(-200., 180.)
+    ));
+    let shape_ink = ink_spent(&mut sim);
+    assert!(shape_ink > 0 && shape_ink < 150);
+
+    sim.draw_freehand(Vec2::new(-800., 0.), Vec2::new(800., 0.));
+    sim.step(1);
+    assert_eq!(ink_spent(&mut sim), 150);
+    assert_eq!(count_entities::<With<DrawnObstacle>>(&mut sim), 2);
+}
+
+#[test]
+fn shapes_cost_ink_like_strokes() {
+    let mut sim = HeadlessSimulation::new(6);
+    // a 400 by 300 rectangle has a 1400 long outline, more than the 150 ink
+    assert!(!sim.place_shape(
+        Tools::Rectangle,
+        Vec2::new(-200., 0.),
+        Vec2::new(200., 300.)
+    ));
+    assert!(sim.place_shape(Tools::Line, Vec2::new(-200., 0.), Vec2::new(200., 0.)));
+    sim.step(1);
+    assert_eq!(ink_spent(&mut sim), 51);
+
+    sim.undo();
+    sim.step(1);
+    assert_eq!(ink_spent(&mut sim), 0);
+}
+
+#[test]
+fn clicking_without_dragging_places_no_shape() {
+    let mut sim = HeadlessSimulation::new(6);
+    for tool in [Tools::Line, Tools::Rectangle, Tools::Funnel] {
+        assert!(!sim.place_shape(tool, Vec2::new(10., 10.), Vec2::new(13., 12.)));
+    }
+    sim.step(1);
+    assert_eq!(count_entities::<With<DrawnObstacle>>(&mut sim), 0);
+}
+
+#[test]
+fn shapes_snap_to_the_grid() {
+    let grid = GridSnap(true);
+    assert_eq!(grid.snap(Vec2::new(17., -23.)), Vec2::new(0., -40.));
+    assert_eq!(
+        GridSnap(false).snap(Vec2::new(17., -23.)),
+        Vec2::new(17., -23.)
+    );
+}
+
+#[test]
+fn buying_counts_money_not_applied_yet() {
+    // 200 money, the first crafter leaves exactly its cost of 100
+    let mut sim = HeadlessSimulation::new(3);
+    assert!(sim.buy_machine(0, Vec2::new(-300., 100.)));
+    assert!(!sim.buy_machine(0, Vec2::new(300., 100.)));
+    sim.step(1);
+    assert_eq!(sim.app.world().resource::<CurrentMoney>().0, 100);
+    assert_eq!(count_entities::<With<Machine>>(&mut sim), 1);
+}
+
+#[test]
+fn selling_a_machine_refunds_half_its_cost() {
+    let mut sim = HeadlessSimulation::new(3);
+    assert!(sim.buy_machine(0, Vec2::new(0., 100.)));
+    sim.step(1);
+    // the crafter costs 100 of the 200 starting money
+    assert_eq!(sim.app.world().resource::<CurrentMoney>().0, 100);
+    let world = sim.app.world_mut();
+    let machine = world
+        .query_filtered::<Entity, With<Machine>>()
+        .single(world);
+
+    assert!(sim.sell_machine(machine));
+    sim.step(1);
+    assert_eq!(sim.app.world().resource::<CurrentMoney>().0, 150);
+    assert_eq!(count_entities::<With<Machine>>(&mut sim), 0);
+    assert_eq!(count_entities::<With<FollowWithOffset>>(&mut sim), 0);
+    assert!(!sim.sell_machine(machine));
+}
+
+fn blocked_at(sim: &mut HeadlessSimulation, position: Vec2) -> bool {
+    const CRAFTER: Machine = Machine::OneToOneCrafter {
+        input: Item::Steel,
+        output: Item::Bolt,
+        cost: 100,
+    };
+    sim.app
+        .world_mut()
+        .run_system_once(move |obstacles: PlacementObstacles| {
+            placement_blocked(&CRAFTER, position, &obstacles)
+        })
+        .unwrap()
+}
+
+#[test]
+fn machines_cant_be_placed_on_other_colliders() {
+    let mut sim = HeadlessSimulation::new(3);
+    assert!(!blocked_at(&mut sim, Vec2::new(0., 100.)));
+    assert!(sim.buy_machine(0, Vec2::new(0., 100.)));
+    sim.step(1);
+    assert!(blocked_at(&mut sim, Vec2::new(50., 100.)));
+    assert!(!blocked_at(&mut sim, Vec2::new(0., -200.)));
+}
+
+#[test]
+fn strokes_only_block_the_spots_they_pass() {
+    let mut sim = HeadlessSimulation::new(3);
+    draw(&mut sim, (-300., 300.), (300., -300.));
+    sim.step(1);
+    assert!(blocked_at(&mut sim, Vec2::new(0., 0.)));
+    // inside the stroke's bounding box, but well clear of the stroke
+    assert!(!blocked_at(&mut sim, Vec2::new(200., 200.)));
+    assert!(!blocked_at(&mut sim, Vec2::new(-200., -200.)));
+}
+
+fn placing(sim: &HeadlessSimulation) -> bool {
+    sim.app.world().resource::<MachinePlacement>().0.is_some()
+}
+
+#[test]
+fn money_is_only_taken_once_the_machine_is_placed() {
+    let mut sim = HeadlessSimulation::new(3);
+    assert!(sim.pick_machine(0));
+    sim.step(1);
+    assert!(placing(&sim));
+    assert_eq!(count_entities::<With<PlacementGhost>>(&mut sim), 1);
+    assert_eq!(sim.report().money, 200);
+
+    assert!(sim.click_placement(Vec2::new(0., 100.)));
+    sim.step(1);
+    assert!(!placing(&sim));
+    assert_eq!(count_entities::<With<PlacementGhost>>(&mut sim), 0);
+    assert_eq!(count_entities::<With<Machine>>(&mut sim), 1);
+    assert_eq!(sim.report().money, 100);
+}
+
+#[test]
+fn blocked_spots_are_refused_while_placing() {
+    let mut sim = HeadlessSimulation::new(3);
+    draw(&mut sim, (-100., 100.), (100., 100.));
+    assert!(sim.pick_machine(0));
+    assert!(!sim.click_placement(Vec2::new(0., 100.)));
+    sim.step(1);
+    assert!(placing(&sim));
+    assert_eq!(sim.report().money, 200);
+
+    assert!(sim.click_placement(Vec2::new(0., -200.)));
+    sim.step(1);
+    assert_eq!(sim.report().money, 100);
+}
+
+#[test]
+fn escape_cancels_placing_instead_of_pausing() {
+    let mut sim = HeadlessSimulation::new(3);
+    assert!(sim.pick_machine(0));
+    sim.press_key(KeyCode::Escape);
+    assert!(!placing(&sim));
+    assert_eq!(count_entities::<With<PlacementGhost>>(&mut sim), 0);
+    assert_eq!(count_entities::<With<Machine>>(&mut sim), 0);
+    assert_eq!(sim.report().money, 200);
+    assert_eq!(
+        *sim.app.world().resource::<State<LevelState>>().get(),
+        LevelState::Playing
+    );
+
+    // without a machine to place, escape pauses again
+    sim.press_key(KeyCode::Escape);
+    assert_eq!(
+        *sim.app.world().resource::<State<LevelState>>().get(),
+        LevelState::Paused
+    );
+}
+
+#[test]
+fn shop_slots_can_be_limited() {
+    let source = r#"(
+        starting_money: 100,
+        money_goal: 200,
+        background: City,
+        machine_shop: [
+            Duplicator(cost: 10),
+            Duplicator(cost: 20),
+        ],
+        machine_limits: {1: 2},
+    )"#;
+    let level = LevelDefinition::parse("level_test.ron".into(), source).unwrap();
+    assert_eq!(ShopStock::from_level(&level).0, vec![None, Some(2)]);
+
+    let missing_slot = source.replace("{1: 2}", "{2: 2}");
+    assert!(LevelDefinition::parse("level_test.ron".into(), &missing_slot).is_err());
+}
+
+#[test]
+fn sold_out_machines_cant_be_bought() {
+    let mut sim = HeadlessSimulation::new(3);
+    let world = sim.app.world_mut();
+    world.resource_mut::<CurrentMoney>().0 = 1000;
+    world.resource_mut::<ShopStock>().0 = vec![Some(1)];
+
+    assert!(sim.buy_machine(0, Vec2::new(0., 100.)));
+    assert!(!sim.buy_machine(0, Vec2::new(0., -200.)));
+    sim.step(1);
+    assert_eq!(sim.app.world().resource::<CurrentMoney>().0, 900);
+    assert!(!sim.app.world().resource::<ShopStock>().available(0));
+}
+
+#[test]
+fn selling_a_machine_puts_it_back_in_stock() {
+    let mut sim = HeadlessSimulation::new(3);
+    let world = sim.app.world_mut();
+    world.resource_mut::<CurrentMoney>().0 = 1000;
+    world.resource_mut::<ShopStock>().0 = vec![Some(1)];
+
+    assert!(sim.buy_machine(0, Vec2::new(0., 100.)));
+    sim.step(1);
+    let world = sim.app.world_mut();
+    let machine = world
+        .query_filtered::<Entity, With<Machine>>()
+        .single(world);
+    assert_eq!(world.get::<ShopSlot>(machine), Some(&ShopSlot(0)));
+
+    assert!(sim.sell_machine(machine));
+    sim.step(1);
+    assert!(sim.app.world().resource::<ShopStock>().available(0));
+    assert!(sim.buy_machine(0, Vec2::new(0., 100.)));
+}
+
+// fixed ticks simulated, `SimulationReport::ticks` counts updates
+fn level_ticks(sim: &HeadlessSimulation) -> u32 {
+    sim.app.world().resource::<SimulationStats>().ticks
+}
+
+fn set_time_scale(sim: &mut HeadlessSimulation, scale: f32) {
+    sim.app.world_mut().resource_mut::<TimeScale>().0 = scale;
+    // applied at the end of this update, the next one runs at the new speed
+    sim.step(1);
+}
+
+#[test]
+fn time_scale_changes_ticks_per_update() {
+    let mut sim = HeadlessSimulation::new(1);
+    set_time_scale(&mut sim, 2.0);
+    let ticks = level_ticks(&sim);
+    sim.step(10);
+    assert_eq!(level_ticks(&sim), ticks + 20);
+
+    // paused, but the player can still draw
+    set_time_scale(&mut sim, 0.0);
+    let ticks = level_ticks(&sim);
+    sim.step(10);
+    draw(&mut sim, (-100., 0.), (100., 0.));
+    assert_eq!(level_ticks(&sim), ticks);
+    assert!(ink_spent(&mut sim) > 0);
+
+    // a restart is back at normal speed
+    set_level_state(&mut sim, LevelState::Loading);
+    sim.step(2);
+    assert_eq!(sim.app.world().resource::<TimeScale>().0, 1.0);
+}
+
+fn static_colliders(sim: &HeadlessSimulation) -> usize {
+    sim.app
+        .world()
+        .resource::<Broadphase>()
+        .static_colliders
+        .len()
+}
+
+#[test]
+fn erasing_at_zero_speed_leaves_the_broadphase() {
+    let mut sim = HeadlessSimulation::new(1);
+    draw(&mut sim, (-100., 0.), (100., 0.));
+    sim.step(1);
+    let colliders = static_colliders(&sim);
+
+    // no physics tick runs, the removal is still picked up
+    set_time_scale(&mut sim, 0.0);
+    sim.erase_line(Vec2::new(-100., 0.), Vec2::new(100., 0.));
+    assert_eq!(count_entities::<With<DrawnObstacle>>(&mut sim), 0);
+    sim.step(10);
+    assert_eq!(static_colliders(&sim), colliders - 1);
+
+    // and nothing of the level stays in it once another one is loaded
+    sim.app.world_mut().resource_mut::<CurrentLevel>().0 = 2;
+    set_level_state(&mut sim, LevelState::Loading);
+    sim.step(2);
+    let world = sim.app.world_mut();
+    let level_colliders = world
+        .query_filtered::<Entity, (
+            Without<Velocity>,
+            Or<(
+                With<CirclePhysics>,
+                With<ColliderCollection>,
+                With<StrokePhysics>,
+            )>,
+        )>()
+        .iter(world)
+        .count();
+    assert_eq!(static_colliders(&sim), level_colliders);
+}
+
+fn level_state(sim: &HeadlessSimulation) -> LevelState {
+    *sim.app.world().resource::<State<LevelState>>().get()
+}
+
+fn press_ctrl_z(sim: &mut HeadlessSimulation) {
+    let world = sim.app.world_mut();
+    world
+        .resource_mut::<ButtonInput<KeyCode>>()
+        .press(KeyCode::ControlLeft);
+    sim.press_key(KeyCode::KeyZ);
+    let world = sim.app.world_mut();
+    world
+        .resource_mut::<ButtonInput<KeyCode>>()
+        .release(KeyCode::ControlLeft);
+}
+
+#[test]
+fn pause_menu_stops_the_clock_like_zero_speed() {
+    let mut sim = HeadlessSimulation::new(1);
+    set_time_scale(&mut sim, 2.0);
+    draw(&mut sim, (-100., 0.), (100., 0.));
+
+    sim.press_key(KeyCode::Escape);
+    assert_eq!(level_state(&sim), LevelState::Paused);
+    assert_eq!(sim.app.world().resource::<TimeScale>().0, 0.0);
+    // the menu covers the level, nothing can be edited under it
+    press_ctrl_z(&mut sim);
+    assert_eq!(count_entities::<With<DrawnObstacle>>(&mut sim), 1);
+
+    // closing it goes back to the speed from before
+    sim.press_key(KeyCode::Escape);
+    assert_eq!(level_state(&sim), LevelState::Playing);
+    assert_eq!(sim.app.world().resource::<TimeScale>().0, 2.0);
+
+    // at 0x the level stays open for editing
+    set_time_scale(&mut sim, 0.0);
+    press_ctrl_z(&mut sim);
+    assert_eq!(count_entities::<With<DrawnObstacle>>(&mut sim), 0);
+}
+
+#[test]
+fn input_pipes_stay_locked_in_the_build_phase() {
+    let mut sim = HeadlessSimulation::new(5);
+    let report = run_with_input_enabled(&mut sim);
+    assert_eq!(report.items_spawned, 0);
+    assert_eq!(report.money, 5000);
+}
+
+#[test]
+fn resetting_a_run_keeps_the_layout() {
+    let mut sim = HeadlessSimulation::new(5);
+    assert!(sim.buy_machine(0, Vec2::new(-300., 250.)));
+    sim.step(1);
+    let money = sim.report().money;
+    sim.start_run();
+    // machines can only be bought while building
+    assert!(!sim.buy_machine(0, Vec2::new(300., 250.)));
+    sim.step(120);
+    assert!(sim.report().items_spawned > 0);
+    assert_ne!(sim.report().money, money);
+    assert!(
+        sim.app
+            .world()
+            .resource::<IncomeMeter>()
+            .expenses_per_second()
+            > 0.0
+    );
+    let world = sim.app.world_mut();
+    for mut pipe in world.query::<&mut OutputPipe>().iter_mut(world) {
+        pipe.quality = 0.5;
+    }
+
+    sim.reset_run();
+    sim.step(1);
+    let report = sim.report();
+    assert_eq!(report.money, money);
+    assert_eq!(report.items_spawned, 0);
+    assert_eq!(count_entities::<(With<Item>, With<Velocity>)>(&mut sim), 0);
+    assert_eq!(count_entities::<With<Machine>>(&mut sim), 1);
+    assert_eq!(*sim.app.world().resource::<RunPhase>(), RunPhase::Building);
+    let income = sim.app.world().resource::<IncomeMeter>();
+    assert_eq!(income.income_per_second(), 0.0);
+    assert_eq!(income.expenses_per_second(), 0.0);
+    let world = sim.app.world_mut();
+    assert!(world
+        .query::<&OutputPipe>()
+        .iter(world)
+        .all(|pipe| pipe.quality == 1.0));
+}
+
+#[test]
+fn output_pipes_can_set_what_happens_to_wrong_items() {
+    let source = r#"(
+        starting_money: 100,
+        money_goal: 200,
+        background: City,
+        output_pipes: [
+            (slot: 1, item: Bolt, reward: 10),
+            (slot: 2, item: Bolt, reward: 10, wrong_items: Penalty(20)),
+            (slot: 3, item: Bolt, reward: 10, wrong_items: Contaminate(0.25)),
+        ],
+    )"#;
+    let level = LevelDefinition::parse("level_test.ron".into(), source).unwrap();
+    let wrong_items: Vec<WrongItems> = level
+        .output_pipes
+        .iter()
+        .map(|pipe| pipe.wrong_items)
+        .collect();
+    assert_eq!(
+        wrong_items,
+        vec![
+            WrongItems::Ignore,
+            WrongItems::Penalty(20),
+            WrongItems::Contaminate(0.25)
+        ]
+    );
+}
+
+#[test]
+fn income_meter_averages_the_last_seconds() {
+    // level 1 spawns 5 bolts a second for 1 money each, kept from being won
+    let mut sim = HeadlessSimulation::new(1);
+    sim.app.world_mut().resource_mut::<MoneyGoal>().0 = 100_000;
+    sim.set_input_pipes_enabled(true);
+    sim.step(10 * 60);
+    let expenses = sim
+        .app
+        .world()
+        .resource::<IncomeMeter>()
+        .expenses_per_second();
+    assert!((expenses - 5.0).abs() <= 0.2, "{} per second", expenses);
+
+    // nothing spent once the transactions are older than the window
+    sim.set_input_pipes_enabled(false);
+    sim.step(6 * 60);
+    let expenses = sim
+        .app
+        .world()
+        .resource::<IncomeMeter>()
+        .expenses_per_second();
+    assert_eq!(expenses, 0.0);
+}
+
+#[test]
+fn every_money_change_is_in_the_ledger() {
+    let mut sim = HeadlessSimulation::new(3);
+    assert!(sim.buy_machine(0, Vec2::new(0., 100.)));
+    sim.set_input_pipes_enabled(true);
+    sim.step(120);
+
+    let world = sim.app.world_mut();
+    let machine = world
+        .query_filtered::<Entity, With<Machine>>()
+        .single(world);
+    let world = sim.app.world();
+    let ledger = &world.resource::<MoneyLedger>().0;
+    assert_eq!(
+        ledger[0],
+        MoneyChanged {
+            amount: -100,
+            reason: MoneyReason::MachineBought,
+            source_entity: Some(machine),
+        }
+    );
+    let spawned = ledger
+        .iter()
+        .filter(|change| change.reason == MoneyReason::ItemSpawned)
+        .count();
+    assert_eq!(spawned as u32, sim.report().items_spawned);
+    let total: i32 = ledger.iter().map(|change| change.amount).sum();
+    assert_eq!(world.resource::<CurrentMoney>().0, 200 + total);
+}
@@ -0,0 +1,362 @@
+use assembly_anarchy::game::*;
+use bevy::ecs::system::RunSystemOnce;
+use bevy::prelude::*;
+
+const ASSEMBLER: Machine = Machine::TwoToOneCrafter {
+    inputs: [Item::Iron, Item::Bolt],
+    output: Item::Wrench,
+    cost: 0,
+};
+
+// an empty level: the input pipes of level 1 stay switched off
+fn empty_level() -> HeadlessSimulation {
+    HeadlessSimulation::new(1)
+}
+
+fn place_machine(sim: &mut HeadlessSimulation, machine: Machine, position: Vec2) {
+    sim.app
+        .world_mut()
+        .run_system_once(move |commands: Commands| {
+            spawn_machine(commands, Position(position), machine)
+        })
+        .unwrap();
+}
+
+fn drop_item(sim: &mut HeadlessSimulation, item: Item, position: Vec2) {
+    sim.app.world_mut().spawn((
+        item,
+        Position(position),
+        CirclePhysics { radius: 14.0 },
+        Velocity(Vec2::ZERO),
+    ));
+}
+
+fn count(sim: &mut HeadlessSimulation, item: Item) -> usize {
+    let world = sim.app.world_mut();
+    let mut items = world.query::<&Item>();
+    items.iter(world).filter(|other| **other == item).count()
+}
+
+#[test]
+fn two_to_one_crafter_waits_for_both_inputs() {
+    let mut sim = empty_level();
+    place_machine(&mut sim, ASSEMBLER, Vec2::ZERO);
+
+    drop_item(&mut sim, Item::Iron, Vec2::new(-80., 150.));
+    sim.step(60);
+    assert_eq!(count(&mut sim, Item::Iron), 0);
+    assert_eq!(count(&mut sim, Item::Wrench), 0);
+
+    // the iron slot is taken, so a second iron has to wait in the intake
+    drop_item(&mut sim, Item::Iron, Vec2::new(80., 150.));
+    sim.step(60);
+    assert_eq!(count(&mut sim, Item::Iron), 1);
+    assert_eq!(count(&mut sim, Item::Wrench), 0);
+
+    // completing the pair releases the product and makes room for the waiting iron
+    drop_item(&mut sim, Item::Bolt, Vec2::new(-80., 150.));
+    sim.step(60);
+    assert_eq!(count(&mut sim, Item::Bolt), 0);
+    assert_eq!(count(&mut sim, Item::Iron), 0);
+    assert_eq!(count(&mut sim, Item::Wrench), 1);
+}
+
+#[test]
+fn two_to_one_crafter_bounces_other_items() {
+    let mut sim = empty_level();
+    place_machine(&mut sim, ASSEMBLER, Vec2::ZERO);
+
+    drop_item(&mut sim, Item::Gold, Vec2::new(80., 150.));
+    let mut bounced = false;
+    for _ in 0..60 {
+        sim.step(1);
+        let world = sim.app.world_mut();
+        let velocity = world
+            .query_filtered::<&Velocity, With<Item>>()
+            .single(world)
+            .0;
+        bounced |= velocity.y > 0.;
+    }
+    assert!(bounced);
+    assert_eq!(count(&mut sim, Item::Gold), 1);
+    assert_eq!(count(&mut sim, Item::Wrench), 0);
+}
+
+fn item_x(sim: &mut HeadlessSimulation, item: Item) -> Vec<f32> {
+    let world = sim.app.world_mut();
+    let mut items = world.query::<(&Item, &Position)>();
+    items
+        .iter(world)
+        .filter(|(other, _)| **other == item)
+        .map(|(_, pos)| pos.0.x)
+        .collect()
+}
+
+#[test]
+fn sorter_routes_matching_items_left_and_others_right() {
+    let mut sim = empty_level();
+    place_machine(
+        &mut sim,
+        Machine::Sorter {
+            item: Item::Iron,
+            cost: 0,
+        },
+        Vec2::ZERO,
+    );
+
+    drop_item(&mut sim, Item::Iron, Vec2::new(0., 150.));
+    drop_item(&mut sim, Item::Gold, Vec2::new(0., 250.));
+    sim.step(60);
+
+    // nothing is destroyed, only routed
+    let iron = item_x(&mut sim, Item::Iron);
+    let gold = item_x(&mut sim, Item::Gold);
+    assert_eq!(iron.len(), 1);
+    assert_eq!(gold.len(), 1);
+    assert!(iron[0] < -60., "iron left at x = {}", iron[0]);
+    assert!(gold[0] > 60., "gold left at x = {}", gold[0]);
+}
+
+fn item_carried_by_conveyor(direction: BeltDirection) -> f32 {
+    let mut sim = empty_level();
+    place_machine(
+        &mut sim,
+        Machine::Conveyor {
+            direction,
+            speed: 120.0,
+            cost: 0,
+        },
+        Vec2::ZERO,
+    );
+    drop_item(&mut sim, Item::Iron, Vec2::new(0., 40.));
+    sim.step(30);
+    item_x(&mut sim, Item::Iron)[0]
+}
+
+#[test]
+fn conveyor_carries_items_in_its_direction() {
+    let right = item_carried_by_conveyor(BeltDirection::Right);
+    let left = item_carried_by_conveyor(BeltDirection::Left);
+    assert!(right > 20., "item only reached x = {}", right);
+    assert!(left < -20., "item only reached x = {}", left);
+}
+
+#[test]
+fn conveyor_ends_are_plain_walls() {
+    let mut sim = empty_level();
+    place_machine(
+        &mut sim,
+        Machine::Conveyor {
+            direction: BeltDirection::Right,
+            speed: 120.0,
+            cost: 0,
+        },
+        Vec2::ZERO,
+    );
+    // thrown at the left end of the 200 wide belt
+    sim.app.world_mut().spawn((
+        Item::Iron,
+        Position(Vec2::new(-130., 0.)),
+        CirclePhysics { radius: 14.0 },
+        Velocity(Vec2::new(300., 0.)),
+    ));
+    let mut bounced = false;
+    for _ in 0..20 {
+        sim.step(1);
+        let world = sim.app.world_mut();
+        let velocity = world
+            .query_filtered::<&Velocity, With<Item>>()
+            .single(world)
+            .0;
+        // only gravity pulls it down, the belt doesn't launch it up
+        assert!(velocity.y <= 0., "{:?}", velocity);
+        bounced |= velocity.x < 0.;
+    }
+    assert!(bounced);
+}
+
+const CRAFTER: Machine = Machine::OneToOneCrafter {
+    input: Item::Iron,
+    output: Item::Wrench,
+    cost: 0,
+};
+
+#[test]
+fn crafter_takes_its_processing_time() {
+    let mut sim = empty_level();
+    place_machine(&mut sim, CRAFTER, Vec2::ZERO);
+    drop_item(&mut sim, Item::Iron, Vec2::new(0., 40.));
+
+    sim.step(2);
+    assert_eq!(count(&mut sim, Item::Iron), 0);
+    assert_eq!(count(&mut sim, Item::Wrench), 0);
+
+    // 0.1s at 60 ticks per second
+    sim.step(6);
+    assert_eq!(count(&mut sim, Item::Wrench), 1);
+}
+
+#[test]
+fn full_machine_bounces_items_off() {
+    let mut sim = empty_level();
+    place_machine(&mut sim, CRAFTER, Vec2::ZERO);
+    sim.step(1);
+    let world = sim.app.world_mut();
+    let mut queue = world.query::<&mut ProcessingQueue>().single_mut(world);
+    queue.products.extend([Item::Wrench; 4]);
+
+    drop_item(&mut sim, Item::Iron, Vec2::new(0., 0.));
+    sim.step(1);
+    let world = sim.app.world_mut();
+    let (_, velocity) = world.query::<(&Item, &Velocity)>().single(world);
+    assert!(velocity.0.y > 0.);
+}
+
+fn turn_machine(sim: &mut HeadlessSimulation, angle: f32) {
+    let world = sim.app.world_mut();
+    world
+        .query_filtered::<&mut Rotation, With<Machine>>()
+        .single_mut(world)
+        .0 = angle;
+}
+
+#[test]
+fn turned_sorter_ejects_along_its_orientation() {
+    let mut sim = empty_level();
+    place_machine(
+        &mut sim,
+        Machine::Sorter {
+            item: Item::Iron,
+            cost: 0,
+        },
+        Vec2::ZERO,
+    );
+    sim.step(1);
+    // a quarter turn counter-clockwise, so the sorter's left points down and its bottom to the right
+    turn_machine(&mut sim, std::f32::consts::FRAC_PI_2);
+
+    drop_item(&mut sim, Item::Iron, Vec2::ZERO);
+    sim.step(1);
+    let iron = item_x(&mut sim, Item::Iron);
+    assert!(iron[0] > 60., "iron left at x = {}", iron[0]);
+}
+
+#[test]
+fn turned_crafter_walls_block_items() {
+    let mut sim = empty_level();
+    place_machine(&mut sim, CRAFTER, Vec2::ZERO);
+    sim.step(1);
+    // lying on its side, the walls become a floor and a roof
+    turn_machine(&mut sim, std::f32::consts::FRAC_PI_2);
+    sim.step(1);
+
+    drop_item(&mut sim, Item::Gold, Vec2::new(0., 200.));
+    sim.step(120);
+    let world = sim.app.world_mut();
+    let (_, pos) = world.query::<(&Item, &Position)>().single(world);
+    assert!(pos.0.y > 50., "gold fell to y = {}", pos.0.y);
+}
+
+// level 1 has a bolt output pipe at the bottom center
+fn set_wrong_items(sim: &mut HeadlessSimulation, wrong_items: WrongItems) {
+    let world = sim.app.world_mut();
+    world
+        .query::<&mut OutputPipe>()
+        .single_mut(world)
+        .wrong_items = wrong_items;
+}
+
+fn output_pipe_quality(sim: &mut HeadlessSimulation) -> f32 {
+    let world = sim.app.world_mut();
+    world.query::<&OutputPipe>().single(world).quality
+}
+
+fn money(sim: &HeadlessSimulation) -> i32 {
+    sim.app.world().resource::<CurrentMoney>().0
+}
+
+#[test]
+fn output_pipes_ignore_wrong_items_by_default() {
+    let mut sim = empty_level();
+    drop_item(&mut sim, Item::Iron, Vec2::new(0., -250.));
+    sim.step(120);
+    // it falls through the pipe for free
+    assert_eq!(money(&sim), 100);
+    assert_eq!(sim.report().items_consumed, 0);
+}
+
+#[test]
+fn rejecting_output_pipes_throw_wrong_items_out() {
+    let mut sim = empty_level();
+    set_wrong_items(&mut sim, WrongItems::Reject);
+    drop_item(&mut sim, Item::Iron, Vec2::new(10., -250.));
+    let mut thrown_out = false;
+    for _ in 0..120 {
+        sim.step(1);
+        let world = sim.app.world_mut();
+        let velocity = world
+            .query_filtered::<&Velocity, With<Item>>()
+            .single(world)
+            .0;
+        // out of the pipe on the side it came in
+        if velocity.y > 0. {
+            assert!(velocity.x > 0., "{:?}", velocity);
+            thrown_out = true;
+            break;
+        }
+    }
+    assert!(thrown_out);
+    assert_eq!(money(&sim), 100);
+}
+
+#[test]
+fn rejecting_output_pipes_leave_rising_items_alone() {
+    let mut sim = empty_level();
+    set_wrong_items(&mut sim, WrongItems::Reject);
+    let world = sim.app.world_mut();
+    let (pipe_pos, view) = world
+        .query_filtered::<(&Position, &SpriteView), With<OutputPipe>>()
+        .single(world);
+    let collection_point = pipe_pos.0 - Vec2::new(0., view.get_scale().y / 2.0);
+    // already on its way out of the pipe
+    world.spawn((
+        Item::Iron,
+        Position(collection_point),
+        CirclePhysics { radius: 14.0 },
+        Velocity(Vec2::new(0., 100.)),
+    ));
+    sim.step(1);
+    let world = sim.app.world_mut();
+    let velocity = world
+        .query_filtered::<&Velocity, With<Item>>()
+        .single(world)
+        .0;
+    assert_eq!(velocity.x, 0.);
+    assert!(velocity.y < 100., "{:?}", velocity);
+}
+
+#[test]
+fn penalizing_output_pipes_take_wrong_items_for_money() {
+    let mut sim = empty_level();
+    set_wrong_items(&mut sim, WrongItems::Penalty(20));
+    drop_item(&mut sim, Item::Iron, Vec2::new(0., -250.));
+    sim.step(120);
+    assert_eq!(count(&mut sim, Item::Iron), 0);
+    assert_eq!(money(&sim), 80);
+}
+
+#[test]
+fn contaminated_output_pipes_pay_less() {
+    let mut sim = empty_level();
+    set_wrong_items(&mut sim, WrongItems::Contaminate(0.4));
+    drop_item(&mut sim, Item::Iron, Vec2::new(0., -250.));
+    sim.step(120);
+    assert_eq!(count(&mut sim, Item::Iron), 0);
+    assert!((output_pipe_quality(&mut sim) - 0.6).abs() < 1e-5);
+
+    // 60% of the reward of 5, and the quality recovers a little
+    drop_item(&mut sim, Item::Bolt, Vec2::new(0., -250.));
+    sim.step(120);
+    assert_eq!(money(&sim), 103);
+    assert!((output_pipe_quality(&mut sim) - (0.6 + QUALITY_RECOVERY)).abs() < 1e-5);
+}
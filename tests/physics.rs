@@ -0,0 +1,79 @@
+use assembly_anarchy::game::*;
+use bevy::prelude::*;
+
+fn item_y(sim: &mut HeadlessSimulation) -> f32 {
+    let world = sim.app.world_mut();
+    world
+        .query_filtered::<&Position, With<Item>>()
+        .single(world)
+        .0
+        .y
+}
+
+#[test]
+fn items_rest_on_a_drawn_stroke() {
+    // level 1 with its input pipe switched off
+    let mut sim = HeadlessSimulation::new(1);
+    sim.draw_line(Vec2::new(-200., 0.), Vec2::new(200., 0.));
+    // between two stroke points, where a chain of circles would leave a dip
+    sim.app.world_mut().spawn((
+        Item::Iron,
+        Position(Vec2::new(STROKE_SPACING / 2., 100.)),
+        CirclePhysics { radius: 14.0 },
+        Velocity(Vec2::ZERO),
+    ));
+    sim.step(120);
+    let resting = item_y(&mut sim);
+    assert!(
+        (resting - (STROKE_RADIUS + 14.0)).abs() < 1.0,
+        "item came to rest at y = {}",
+        resting
+    );
+}
+
+#[test]
+fn items_rest_on_a_drawn_rectangle() {
+    let mut sim = HeadlessSimulation::new(1);
+    assert!(sim.place_shape(
+        Tools::Rectangle,
+        Vec2::new(-200., -20.),
+        Vec2::new(200., 0.)
+    ));
+    sim.app.world_mut().spawn((
+        Item::Iron,
+        Position(Vec2::new(0., 100.)),
+        CirclePhysics { radius: 14.0 },
+        Velocity(Vec2::ZERO),
+    ));
+    // bounces a bit longer than on a stroke
+    sim.step(240);
+    let resting = item_y(&mut sim);
+    assert!(
+        (resting - 14.0).abs() < 1.0,
+        "item came to rest at y = {}",
+        resting
+    );
+}
+
+#[test]
+fn items_stand_still_on_screen_outside_of_playing() {
+    let mut sim = HeadlessSimulation::new(1);
+    sim.app.world_mut().spawn((
+        Item::Iron,
+        Position(Vec2::new(0., 100.)),
+        CirclePhysics { radius: 14.0 },
+        Velocity(Vec2::ZERO),
+    ));
+    // falling, so the last two ticks are apart
+    sim.step(10);
+    sim.app
+        .world_mut()
+        .resource_mut::<NextState<LevelState>>()
+        .set(LevelState::Paused);
+    sim.step(1);
+    let world = sim.app.world_mut();
+    let (pos, previous_pos) = world
+        .query_filtered::<(&Position, &PreviousPosition), With<Item>>()
+        .single(world);
+    assert_eq!(pos.0, previous_pos.0);
+}
@@ -0,0 +1,34 @@
+use assembly_anarchy::game::*;
+
+#[test]
+fn winning_a_level_unlocks_the_next_one() {
+    let mut progress = Progress::default();
+    assert!(progress.is_unlocked(1));
+    assert!(!progress.is_unlocked(2));
+
+    assert!(progress.record_win(1, 30.0));
+    assert!(progress.is_unlocked(2));
+    assert!(!progress.is_unlocked(3));
+
+    // replaying an earlier level doesn't lock anything again
+    progress.record_win(0, 1.0);
+    assert!(progress.is_unlocked(2));
+}
+
+#[test]
+fn only_faster_wins_replace_the_best_time() {
+    let mut progress = Progress::default();
+    progress.record_win(1, 30.0);
+    assert!(!progress.record_win(1, 40.0));
+    assert_eq!(progress.best_times[&1], 30.0);
+    assert!(progress.record_win(1, 20.0));
+    assert_eq!(progress.best_times[&1], 20.0);
+}
+
+#[test]
+fn progress_survives_a_save_round_trip() {
+    let mut progress = Progress::default();
+    progress.record_win(7, 12.5);
+    let source = ron::to_string(&progress).unwrap();
+    assert_eq!(ron::from_str::<Progress>(&source).unwrap(), progress);
+}